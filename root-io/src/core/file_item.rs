@@ -1,7 +1,8 @@
 use failure::Error;
-use nom::multi::length_value;
+use nom::{multi::length_value, IResult};
 
-use crate::core::{checked_byte_count, decompress, Context, Source, TKeyHeader};
+use crate::core::{checked_byte_count, decompress, tfolder, Context, Folder, Source, TKeyHeader};
+use crate::hist_reader::{th1, th2, tprofile, Histogram1D, Histogram2D, Profile};
 use crate::tree_reader::{ttree, Tree};
 
 /// Describes a single item within this file (e.g. a `Tree`)
@@ -31,6 +32,16 @@ impl FileItem {
         )
     }
 
+    /// Name of the object stored under this key (e.g. `histos`)
+    pub fn obj_name(&self) -> &str {
+        &self.tkey_hdr.obj_name
+    }
+
+    /// Class name of the object stored under this key (e.g. `TFolder`)
+    pub fn class_name(&self) -> &str {
+        &self.tkey_hdr.class_name
+    }
+
     async fn get_buffer(&self) -> Result<Vec<u8>, Error> {
         let start = self.tkey_hdr.seek_key + self.tkey_hdr.key_len as u64;
         let len = self.tkey_hdr.total_size - self.tkey_hdr.key_len as u32;
@@ -56,20 +67,54 @@ impl FileItem {
         })
     }
 
-    /// Parse this `FileItem` as a `Tree`
-    pub async fn as_tree(&self) -> Result<Tree, Error> {
+    /// Parse the (decompressed) payload of this `FileItem` with the
+    /// given parser. The parser is handed the object without its
+    /// leading byte count and the `Context` needed to resolve class
+    /// references within the buffer.
+    pub async fn parse_with<F, O>(&self, parser: F) -> Result<O, Error>
+    where
+        F: for<'s> Fn(&'s [u8], &'s Context) -> IResult<&'s [u8], O>,
+    {
         let ctx = self.get_context().await?;
         let buf = ctx.s.as_slice();
 
-        let res = length_value(checked_byte_count, |i| ttree(i, &ctx))(buf);
+        let res = length_value(checked_byte_count, |i| parser(i, &ctx))(buf);
         match res {
             Ok((_, obj)) => Ok(obj),
-            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-                Err(format_err!("Supplied parser failed! Error Code: {:?}", e.code))
-            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(format_err!(
+                "Supplied parser failed! Error Code: {:?}",
+                e.code
+            )),
             _ => panic!(),
         }
     }
+
+    /// Parse this `FileItem` as a `Tree`
+    pub async fn as_tree(&self) -> Result<Tree, Error> {
+        self.parse_with(ttree).await
+    }
+
+    /// Parse this `FileItem` as a `Folder`
+    pub async fn as_folder(&self) -> Result<Folder, Error> {
+        self.parse_with(tfolder).await
+    }
+
+    /// Parse this `FileItem` as a one dimensional histogram (`TH1F`, `TH1D`, `TH1I` or `TH1S`)
+    pub async fn as_th1(&self) -> Result<Histogram1D, Error> {
+        self.parse_with(|i, ctx| th1(self.class_name(), i, ctx))
+            .await
+    }
+
+    /// Parse this `FileItem` as a two dimensional histogram (`TH2F` or `TH2D`)
+    pub async fn as_th2(&self) -> Result<Histogram2D, Error> {
+        self.parse_with(|i, ctx| th2(self.class_name(), i, ctx))
+            .await
+    }
+
+    /// Parse this `FileItem` as a `TProfile`
+    pub async fn as_profile(&self) -> Result<Profile, Error> {
+        self.parse_with(tprofile).await
+    }
 }
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
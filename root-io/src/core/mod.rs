@@ -6,6 +6,7 @@ mod data_source;
 mod file;
 mod file_item;
 pub mod parsers;
+mod tfolder;
 mod tkey;
 mod tstreamer;
 mod tstreamerinfo;
@@ -22,3 +23,4 @@ pub(crate) use self::types::*;
 pub use self::data_source::Source;
 pub use self::file::RootFile;
 pub use self::file_item::FileItem;
+pub use self::tfolder::{tfolder, Folder, FolderItem};
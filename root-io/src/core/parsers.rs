@@ -69,8 +69,13 @@ pub fn tobject(input: &[u8]) -> nom::IResult<&[u8], TObject> {
 
 /// Parse a `TList`
 pub fn tlist<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Vec<Raw<'s>>> {
+    map(|i| named_tlist(i, ctx), |(_name, objs)| objs)(i)
+}
+
+/// Parse a `TList` together with its name
+pub fn named_tlist<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], (String, Vec<Raw<'s>>)> {
     let (i, _ver) = verify(be_u16, |&v| v == 5)(i)?;
-    let (i, (_tobj, _name, len)) = tuple((tobject, string, be_i32))(i)?;
+    let (i, (_tobj, name, len)) = tuple((tobject, string, be_i32))(i)?;
     let (i, objs) = count(
         |i| {
             let wrapped_raw = |i| raw(i, ctx);
@@ -81,7 +86,7 @@ pub fn tlist<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Vec<Raw<'s>
         len as usize,
     )(i)?;
     let (i, _) = rest(i)?;
-    Ok((i, objs))
+    Ok((i, (name, objs)))
 }
 
 /// Parser for `TNamed` objects
@@ -123,6 +128,26 @@ where
     Ok((i, objs))
 }
 
+/// Parse a `TObjArray` together with its name. Contrary to
+/// `tobjarray`, the elements are returned as `Raw` objects and empty
+/// slots (null pointers) are skipped.
+pub fn named_tobjarray<'s>(
+    i: &'s [u8],
+    context: &'s Context,
+) -> nom::IResult<&'s [u8], (String, Vec<Raw<'s>>)> {
+    let (i, _ver) = be_u16(i)?;
+    let (i, _tobj) = tobject(i)?;
+    let (i, name) = string(i)?;
+    let (i, size) = be_i32(i)?;
+    let (i, _low) = be_i32(i)?;
+    let (i, objs) = count(|i| raw(i, context), size as usize)(i)?;
+    let objs = objs
+        .into_iter()
+        .filter(|r| !r.classinfo.is_empty())
+        .collect();
+    Ok((i, (name, objs)))
+}
+
 /// Parse a `TObjArray` which does not have references pointing outside of the input buffer
 pub fn tobjarray_no_context(input: &[u8]) -> nom::IResult<&[u8], Vec<(ClassInfo, &[u8])>> {
     let (input, _ver) = be_u16(input)?;
@@ -0,0 +1,236 @@
+use nom::{
+    branch::alt,
+    combinator::map,
+    multi::length_value,
+    number::complete::{be_u16, be_u8},
+    sequence::pair,
+    IResult,
+};
+
+use crate::core::*;
+use crate::hist_reader::{th1, th2, tprofile, Histogram1D, Histogram2D, Profile};
+
+/// A `TFolder` is ROOT's way of organizing objects in a hierarchy
+/// without using a `TDirectory`. Files written by e.g. the MusrRoot
+/// data acquisition store all their content in such folders.
+#[derive(Debug)]
+pub struct Folder {
+    /// Name of the folder (e.g. `histos`)
+    pub name: String,
+    /// Title of the folder
+    pub title: String,
+    /// Objects contained in this folder
+    pub items: Vec<FolderItem>,
+}
+
+/// An object stored in a `Folder`
+#[derive(Debug)]
+pub enum FolderItem {
+    /// A nested `TFolder`
+    Folder(Folder),
+    /// A named `TList` or `TObjArray`
+    Collection {
+        class_name: String,
+        name: String,
+        items: Vec<FolderItem>,
+    },
+    /// A `TObjString`
+    ObjString(String),
+    /// A one dimensional histogram (`TH1F`, `TH1D`, `TH1I` or `TH1S`)
+    Histogram1D(Box<Histogram1D>),
+    /// A two dimensional histogram (`TH2F` or `TH2D`)
+    Histogram2D(Box<Histogram2D>),
+    /// A `TProfile`
+    Profile(Box<Profile>),
+    /// An object for which no parser is available. `obj` is the
+    /// streamed object without its leading byte count. References to
+    /// class definitions outside of this object cannot be resolved
+    /// from it anymore.
+    Raw {
+        class_name: String,
+        name: String,
+        obj: Vec<u8>,
+    },
+}
+
+impl Folder {
+    /// Look up an item by its `/`-separated path relative to this
+    /// folder, e.g. `DecayAnaModule/hDecay001`. The path may also
+    /// start with the name of this folder itself, e.g.
+    /// `histos/DecayAnaModule/hDecay001`.
+    pub fn get(&self, path: &str) -> Option<&FolderItem> {
+        lookup(&self.items, path).or_else(|| {
+            let (head, tail) = path.trim_start_matches('/').split_once('/')?;
+            if head == self.name {
+                lookup(&self.items, tail)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl FolderItem {
+    /// The name of this item. The name of a `TObjString` is its content.
+    pub fn name(&self) -> &str {
+        match self {
+            FolderItem::Folder(f) => &f.name,
+            FolderItem::Collection { name, .. } => name,
+            FolderItem::ObjString(s) => s,
+            FolderItem::Histogram1D(h) => &h.name,
+            FolderItem::Histogram2D(h) => &h.name,
+            FolderItem::Profile(p) => &p.name,
+            FolderItem::Raw { name, .. } => name,
+        }
+    }
+
+    /// The ROOT class name of this item
+    pub fn class_name(&self) -> &str {
+        match self {
+            FolderItem::Folder(_) => "TFolder",
+            FolderItem::Collection { class_name, .. } => class_name,
+            FolderItem::ObjString(_) => "TObjString",
+            FolderItem::Histogram1D(h) => &h.class_name,
+            FolderItem::Histogram2D(h) => &h.class_name,
+            FolderItem::Profile(_) => "TProfile",
+            FolderItem::Raw { class_name, .. } => class_name,
+        }
+    }
+
+    /// The items contained in this item; empty if it is not a folder or collection
+    pub fn items(&self) -> &[FolderItem] {
+        match self {
+            FolderItem::Folder(f) => &f.items,
+            FolderItem::Collection { items, .. } => items,
+            _ => &[],
+        }
+    }
+}
+
+fn lookup<'a>(items: &'a [FolderItem], path: &str) -> Option<&'a FolderItem> {
+    let mut found = None;
+    let mut items = items;
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        let item = items.iter().find(|item| item.name() == component)?;
+        items = item.items();
+        found = Some(item);
+    }
+    found
+}
+
+/// Parse a `TFolder` from the given buffer. Usually used through `FileItem::as_folder`.
+pub fn tfolder<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Folder> {
+    let (i, _ver) = be_u16(i)?;
+    let (i, tnamed) = length_value(checked_byte_count, tnamed)(i)?;
+    let (i, folders) = raw(i, ctx)?;
+    let (i, _is_owner) = be_u8(i)?;
+    let items = if folders.classinfo.is_empty() {
+        // Folder without content
+        vec![]
+    } else {
+        match folder_item(&folders, ctx)? {
+            (_, FolderItem::Collection { items, .. }) => items,
+            (_, item) => vec![item],
+        }
+    };
+    Ok((
+        i,
+        Folder {
+            name: tnamed.name,
+            title: tnamed.title,
+            items,
+        },
+    ))
+}
+
+/// Turn a `Raw` object found in a folder into a `FolderItem`
+fn folder_item<'s>(r: &Raw<'s>, ctx: &'s Context) -> IResult<&'s [u8], FolderItem> {
+    let collection = |(name, objs): (String, Vec<Raw<'s>>)| {
+        let items = objs
+            .iter()
+            .map(|r| folder_item(r, ctx).map(|(_, item)| item))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FolderItem::Collection {
+            class_name: r.classinfo.to_string(),
+            name,
+            items,
+        })
+    };
+    match r.classinfo {
+        "TFolder" => map(|i| tfolder(i, ctx), FolderItem::Folder)(r.obj),
+        "TList" => {
+            let (i, list) = named_tlist(r.obj, ctx)?;
+            Ok((i, collection(list)?))
+        }
+        "TObjArray" => {
+            let (i, arr) = named_tobjarray(r.obj, ctx)?;
+            Ok((i, collection(arr)?))
+        }
+        "TObjString" => map(tobjstring, FolderItem::ObjString)(r.obj),
+        class_name @ ("TH1F" | "TH1D" | "TH1I" | "TH1S") => map(
+            |i| th1(class_name, i, ctx),
+            |h| FolderItem::Histogram1D(Box::new(h)),
+        )(r.obj),
+        class_name @ ("TH2F" | "TH2D") => map(
+            |i| th2(class_name, i, ctx),
+            |h| FolderItem::Histogram2D(Box::new(h)),
+        )(r.obj),
+        "TProfile" => map(|i| tprofile(i, ctx), |p| FolderItem::Profile(Box::new(p)))(r.obj),
+        class_name => Ok((
+            &r.obj[r.obj.len()..],
+            FolderItem::Raw {
+                class_name: class_name.to_string(),
+                name: object_name(r.obj).unwrap_or_default(),
+                obj: r.obj.to_vec(),
+            },
+        )),
+    }
+}
+
+/// Find the name of the `TNamed` at the root of the base class chain
+/// of a streamed object (e.g. TH1F -> TH1 -> TNamed)
+fn object_name(i: &[u8]) -> Option<String> {
+    fn named(i: &[u8]) -> IResult<&[u8], String> {
+        let (i, _ver) = be_u16(i)?;
+        alt((
+            length_value(checked_byte_count, named),
+            map(pair(tobject, string), |(_tobj, name)| name),
+        ))(i)
+    }
+    named(i).ok().map(|(_, name)| name)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::path::Path;
+
+    use super::FolderItem;
+    use crate::core::RootFile;
+
+    #[tokio::test]
+    async fn musr_root_folders() {
+        let path = Path::new("../src/lem24_his_2000.root");
+        let f = RootFile::new(path).await.expect("Failed to open file");
+
+        let histos = f.items()[0].as_folder().await.unwrap();
+        assert_eq!(histos.name, "histos");
+        assert_eq!(histos.title, "MIDAS Analyzer Histograms");
+        let decay = histos.get("DecayAnaModule").unwrap();
+        assert!(matches!(decay, FolderItem::Folder(_)));
+        assert_eq!(decay.items().len(), 32);
+        let hist = histos.get("histos/DecayAnaModule/hDecay001").unwrap();
+        assert!(matches!(hist, FolderItem::Histogram1D(_)));
+        assert_eq!(hist.class_name(), "TH1F");
+        assert!(histos.get("DecayAnaModule/hDecay009").is_none());
+
+        let header = f.items()[1].as_folder().await.unwrap();
+        let run_info = header.get("RunInfo").unwrap();
+        assert_eq!(run_info.class_name(), "TObjArray");
+        assert_eq!(
+            run_info.items()[0].name(),
+            "000 - Version: git-sha: dae9ef0ffba4 -@0"
+        );
+        let detector = header.get("DetectorInfo/Detector001").unwrap();
+        assert_eq!(detector.items().len(), 6);
+    }
+}
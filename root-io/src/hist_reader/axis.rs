@@ -0,0 +1,116 @@
+use nom::{
+    combinator::{cond, map},
+    multi::{length_data, length_value},
+    number::complete::*,
+    sequence::tuple,
+    IResult,
+};
+
+use crate::core::*;
+
+/// A `TAxis` describes the binning of one dimension of a histogram
+#[derive(Debug, Clone)]
+pub struct Axis {
+    /// Name of the axis (usually `xaxis`, `yaxis` or `zaxis`)
+    pub name: String,
+    /// Title of the axis, i.e. the axis label
+    pub title: String,
+    /// Number of bins (excluding under- and overflow)
+    pub nbins: usize,
+    /// Lower edge of the first bin
+    pub xmin: f64,
+    /// Upper edge of the last bin
+    pub xmax: f64,
+    /// Bin edges in case of variable bin widths; empty for fixed bin widths
+    pub edges: Vec<f64>,
+    /// Alphanumeric bin labels as pairs of bin number and label
+    pub labels: Vec<(usize, String)>,
+}
+
+impl Axis {
+    /// Lower edge of bin `bin`, where bin 1 is the first bin within the axis range
+    pub fn bin_low_edge(&self, bin: usize) -> f64 {
+        if !self.edges.is_empty() && bin >= 1 && bin <= self.nbins + 1 {
+            self.edges[bin - 1]
+        } else {
+            self.xmin + (bin as f64 - 1.0) * (self.xmax - self.xmin) / self.nbins as f64
+        }
+    }
+
+    /// Upper edge of bin `bin`
+    pub fn bin_up_edge(&self, bin: usize) -> f64 {
+        self.bin_low_edge(bin + 1)
+    }
+
+    /// Center of bin `bin`
+    pub fn bin_center(&self, bin: usize) -> f64 {
+        0.5 * (self.bin_low_edge(bin) + self.bin_up_edge(bin))
+    }
+
+    /// Width of bin `bin`
+    pub fn bin_width(&self, bin: usize) -> f64 {
+        self.bin_up_edge(bin) - self.bin_low_edge(bin)
+    }
+
+    /// All `nbins + 1` bin edges of this axis
+    pub fn bin_edges(&self) -> Vec<f64> {
+        (1..=self.nbins + 1).map(|b| self.bin_low_edge(b)).collect()
+    }
+
+    /// The bin `x` falls into following ROOT's convention: 0 is the
+    /// underflow and `nbins + 1` the overflow bin
+    pub fn find_bin(&self, x: f64) -> usize {
+        if x < self.xmin {
+            0
+        } else if x >= self.xmax {
+            self.nbins + 1
+        } else if self.edges.is_empty() {
+            1 + ((x - self.xmin) / (self.xmax - self.xmin) * self.nbins as f64) as usize
+        } else {
+            self.edges.partition_point(|&edge| edge <= x)
+        }
+    }
+}
+
+/// Parse a `TAxis`
+pub(crate) fn taxis<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Axis> {
+    let (i, ver) = be_u16(i)?;
+    let (i, tnamed) = length_value(checked_byte_count, tnamed)(i)?;
+    let (i, _tattaxis) = length_data(checked_byte_count)(i)?;
+    let (i, nbins) = be_i32(i)?;
+    let (i, xmin) = be_f64(i)?;
+    let (i, xmax) = be_f64(i)?;
+    let (i, edges) = tarray(be_f64, i)?;
+    let (i, _first) = be_i32(i)?;
+    let (i, _last) = be_i32(i)?;
+    let (i, _bits2) = be_u16(i)?;
+    let (i, _time_display) = be_u8(i)?;
+    let (i, _time_format) = string(i)?;
+    let (i, labels) = raw(i, ctx)?;
+    let (i, _mod_labs) = cond(ver >= 10, |i| raw(i, ctx))(i)?;
+    let labels = if labels.classinfo.is_empty() {
+        vec![]
+    } else {
+        let (_, objs) = tlist(labels.obj, ctx)?;
+        objs.iter()
+            .map(|r| {
+                map(tuple((be_u16, tobject, string)), |(_, o, s)| {
+                    (o.id as usize, s)
+                })(r.obj)
+            })
+            .map(|res| res.map(|(_, label)| label))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    Ok((
+        i,
+        Axis {
+            name: tnamed.name,
+            title: tnamed.title,
+            nbins: nbins as usize,
+            xmin,
+            xmax,
+            edges,
+            labels,
+        },
+    ))
+}
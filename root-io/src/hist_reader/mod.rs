@@ -0,0 +1,270 @@
+//! Parsers and types for ROOT's histogram classes. A histogram
+//! consists of one `Axis` per dimension and a flat array of bin
+//! contents which includes the under- and overflow bins.
+
+mod axis;
+mod th1;
+mod th2;
+mod tprofile;
+
+pub use self::axis::Axis;
+pub use self::th1::{th1, Histogram1D, Stats};
+pub use self::th2::{th2, Histogram2D};
+pub use self::tprofile::{tprofile, ErrorMode, Profile};
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use tokio;
+
+    use super::{th1, th2, tprofile, ErrorMode};
+    use crate::core::{Context, FolderItem, RootFile};
+
+    /// Minimal writer for streamed ROOT objects; only the members read
+    /// by the histogram parsers are filled with meaningful values
+    #[derive(Default)]
+    struct Buf(Vec<u8>);
+
+    impl Buf {
+        fn u8(mut self, v: u8) -> Self {
+            self.0.push(v);
+            self
+        }
+        fn u16(mut self, v: u16) -> Self {
+            self.0.extend(v.to_be_bytes());
+            self
+        }
+        fn i16(mut self, v: i16) -> Self {
+            self.0.extend(v.to_be_bytes());
+            self
+        }
+        fn i32(mut self, v: i32) -> Self {
+            self.0.extend(v.to_be_bytes());
+            self
+        }
+        fn f64(mut self, v: f64) -> Self {
+            self.0.extend(v.to_be_bytes());
+            self
+        }
+        fn string(mut self, s: &str) -> Self {
+            self.0.push(s.len() as u8);
+            self.0.extend(s.as_bytes());
+            self
+        }
+        fn tarray(self, vals: &[f64]) -> Self {
+            vals.iter()
+                .fold(self.i32(vals.len() as i32), |b, &v| b.f64(v))
+        }
+        /// Append `obj` preceded by its byte count
+        fn obj(mut self, obj: Buf) -> Self {
+            self.0
+                .extend((obj.0.len() as u32 | 0x4000_0000).to_be_bytes());
+            self.0.extend(obj.0);
+            self
+        }
+        fn tnamed(self, name: &str, title: &str) -> Self {
+            let tobject = Buf::default().u16(1).i32(0).i32(0x0300_0000);
+            let tnamed = Buf(tobject.0).string(name).string(title);
+            self.obj(Buf::default().u16(1).obj(tnamed))
+        }
+        fn taxis(self, name: &str, nbins: i32, xmin: f64, xmax: f64) -> Self {
+            let axis = Buf::default()
+                .u16(10)
+                .tnamed(name, "")
+                .obj(Buf::default().u16(4))
+                .i32(nbins)
+                .f64(xmin)
+                .f64(xmax)
+                .tarray(&[])
+                .i32(0)
+                .i32(0)
+                .u16(0)
+                .u8(0)
+                .string("")
+                .i32(0)
+                .i32(0);
+            self.obj(axis)
+        }
+        fn th1_base(
+            self,
+            x: (i32, f64, f64),
+            y: (i32, f64, f64),
+            entries: f64,
+            sumw2: &[f64],
+        ) -> Self {
+            let attr = || Buf::default().u16(2);
+            let base = Buf::default()
+                .u16(8)
+                .tnamed("h", "title")
+                .obj(attr())
+                .obj(attr())
+                .obj(attr())
+                .i32(sumw2.len() as i32)
+                .taxis("xaxis", x.0, x.1, x.2)
+                .taxis("yaxis", y.0, y.1, y.2)
+                .taxis("zaxis", 1, 0.0, 1.0)
+                .u16(0)
+                .u16(1000);
+            // fTsumw, fTsumw2, fTsumwx, fTsumwx2, fMaximum, fMinimum, fNormFactor
+            let base = (0..7)
+                .fold(base.f64(entries), |b, _| b.f64(0.0))
+                .tarray(&[])
+                .tarray(sumw2)
+                .string("")
+                .i32(0)
+                .i32(0)
+                .u8(0)
+                .i32(0)
+                .i32(2);
+            self.obj(base)
+        }
+    }
+
+    fn context() -> Context {
+        Context {
+            source: PathBuf::from("").into(),
+            offset: 0,
+            s: vec![],
+        }
+    }
+
+    #[test]
+    fn th1_classes() {
+        let ctx = context();
+        let contents = [1.0, 2.0, 3.0, 4.0];
+        for class_name in ["TH1D", "TH1I", "TH1S"] {
+            // The TArray of the contents holds values of the type of the class
+            let histogram = |contents: &[f64]| {
+                let buf = Buf::default()
+                    .u16(3)
+                    .th1_base((2, 0.0, 2.0), (1, 0.0, 1.0), 9.0, &[])
+                    .i32(contents.len() as i32);
+                contents.iter().fold(buf, |b, &v| match class_name {
+                    "TH1D" => b.f64(v),
+                    "TH1I" => b.i32(v as i32),
+                    _ => b.i16(v as i16),
+                })
+            };
+            let buf = histogram(&contents);
+            let (rest, h) = th1(class_name, &buf.0, &ctx).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(h.class_name, class_name);
+            assert_eq!(h.bins(), [2.0, 3.0]);
+            assert_eq!((h.underflow(), h.overflow()), (1.0, 4.0));
+            assert_eq!(h.bin_error(2), 3f64.sqrt());
+
+            // Contents not matching the axis fail the parse instead of the accessors
+            assert!(th1(class_name, &histogram(&contents[..3]).0, &ctx).is_err());
+        }
+    }
+
+    #[test]
+    fn th2d_bin_access() {
+        let contents: Vec<f64> = (0..20).map(f64::from).collect();
+        let buf = Buf::default()
+            .u16(4)
+            .obj(
+                Buf::default()
+                    .u16(5)
+                    .th1_base((2, 0.0, 2.0), (3, 0.0, 3.0), 6.0, &contents)
+                    .f64(1.0)
+                    .f64(2.0)
+                    .f64(3.0)
+                    .f64(4.0),
+            )
+            .tarray(&contents);
+        let ctx = context();
+        let (rest, h) = th2("TH2D", &buf.0, &ctx).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(h.class_name, "TH2D");
+        assert_eq!((h.nbins_x(), h.nbins_y()), (2, 3));
+        assert_eq!(h.find_bin(1.5, 2.5), (2, 3));
+        assert_eq!(h.bin(2, 3), 14);
+        assert_eq!(h.bin_content(2, 3), 14.0);
+        assert_eq!(h.bin_error(2, 3), 14f64.sqrt());
+        assert_eq!((h.sumwy, h.sumwy2, h.sumwxy), (2.0, 3.0, 4.0));
+        assert!(th2("TH1D", &buf.0, &ctx).is_err());
+    }
+
+    #[test]
+    fn tprofile_means_and_errors() {
+        // Bin 1 filled with y = 1 and y = 3, bin 2 with y = 5
+        let sumwy = [0.0, 4.0, 5.0, 0.0];
+        let sumwy2 = [0.0, 10.0, 25.0, 0.0];
+        let bin_entries = [0.0, 2.0, 1.0, 0.0];
+        let profile = Buf::default()
+            .u16(7)
+            .obj(
+                Buf::default()
+                    .u16(3)
+                    .th1_base((2, 0.0, 2.0), (1, 0.0, 1.0), 3.0, &sumwy2)
+                    .tarray(&sumwy),
+            )
+            .tarray(&bin_entries)
+            .i32(0)
+            .f64(0.0)
+            .f64(0.0)
+            .f64(9.0)
+            .f64(35.0)
+            .tarray(&bin_entries);
+        let ctx = context();
+        let (rest, mut p) = tprofile(&profile.0, &ctx).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(p.error_mode, ErrorMode::Mean);
+        assert_eq!(p.means(), vec![2.0, 5.0]);
+        assert_eq!(p.bin_error(1), 1.0 / 2f64.sqrt());
+        assert_eq!(p.bin_error(2), 0.0);
+        assert_eq!(p.bin_error(0), 0.0);
+        p.error_mode = ErrorMode::Spread;
+        assert_eq!(p.errors(), vec![1.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn musr_root_decay_histogram() {
+        let path = Path::new("../src/lem24_his_2000.root");
+        let f = RootFile::new(path).await.expect("Failed to open file");
+        let histos = f.items()[0].as_folder().await.unwrap();
+
+        let hist = match histos.get("DecayAnaModule/hDecay001") {
+            Some(FolderItem::Histogram1D(h)) => h,
+            other => panic!("Expected a histogram, found {:?}", other.map(|i| i.name())),
+        };
+        assert_eq!(hist.name, "hDecay001");
+        assert_eq!(hist.class_name, "TH1F");
+        assert_eq!(hist.nbins(), 66601);
+        assert_eq!(hist.x_axis.xmin, -0.5);
+        assert_eq!(hist.x_axis.xmax, 66600.5);
+        assert_eq!(hist.contents.len(), 66603);
+        assert_eq!(hist.x_axis.find_bin(0.0), 1);
+        assert_eq!(hist.x_axis.bin_center(1), 0.0);
+        let sum: f64 = hist.contents.iter().sum();
+        assert!((sum - hist.entries).abs() < 1.0);
+
+        let sc = histos.get("SCAnaModule").unwrap();
+        assert!(sc
+            .items()
+            .iter()
+            .all(|item| matches!(item, FolderItem::Histogram1D(_))));
+    }
+
+    #[tokio::test]
+    async fn as_th1_checks_the_class() {
+        // MusrRoot files keep their histograms in TFolders, the keys of the file are the folders
+        let path = Path::new("../src/lem24_his_2000.root");
+        let f = RootFile::new(path).await.expect("Failed to open file");
+        for item in f.items() {
+            assert_eq!(item.class_name(), "TFolder");
+            assert!(item.as_th1().await.is_err());
+        }
+        let histos = f.items()[0].as_folder().await.unwrap();
+        let hist = histos.get("DecayAnaModule/hDecay001").unwrap();
+        assert_eq!(hist.class_name(), "TH1F");
+
+        // The sample-*.root files hold a single TTree and no histograms
+        let path = Path::new("./src/test_data/sample-6.10.05-zlib.root");
+        let f = RootFile::new(path).await.expect("Failed to open file");
+        assert_eq!(f.items().len(), 1);
+        assert_eq!(f.items()[0].class_name(), "TTree");
+        assert!(f.items()[0].as_th1().await.is_err());
+    }
+}
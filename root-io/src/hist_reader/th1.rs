@@ -0,0 +1,193 @@
+use nom::{
+    combinator::{cond, map},
+    error::{make_error, ErrorKind},
+    multi::{count, length_data, length_value},
+    number::complete::*,
+    IResult,
+};
+
+use crate::core::*;
+use crate::hist_reader::axis::{taxis, Axis};
+
+/// Sums accumulated while filling a histogram; used by ROOT to
+/// compute mean and standard deviation independent of the binning
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Total sum of weights
+    pub sumw: f64,
+    /// Total sum of squares of weights
+    pub sumw2: f64,
+    /// Total sum of weight*x
+    pub sumwx: f64,
+    /// Total sum of weight*x*x
+    pub sumwx2: f64,
+}
+
+/// The members of ROOT's `TH1` class which are shared by all histograms
+#[derive(Debug)]
+pub(crate) struct TH1 {
+    pub(crate) tnamed: TNamed,
+    pub(crate) x_axis: Axis,
+    pub(crate) y_axis: Axis,
+    pub(crate) entries: f64,
+    pub(crate) stats: Stats,
+    pub(crate) sumw2: Vec<f64>,
+}
+
+/// A one dimensional histogram (`TH1F`, `TH1D`, `TH1I` or `TH1S`).
+/// Bins are numbered following ROOT's convention: bin 0 is the
+/// underflow, bins `1..=nbins` cover the axis range and bin
+/// `nbins + 1` is the overflow bin.
+#[derive(Debug, Clone)]
+pub struct Histogram1D {
+    /// Name of the histogram (e.g. `hDecay001`)
+    pub name: String,
+    /// Title of the histogram
+    pub title: String,
+    /// ROOT class of the histogram (e.g. `TH1F`)
+    pub class_name: String,
+    /// Binning and label of the abscissa
+    pub x_axis: Axis,
+    /// Only the title (the label of the ordinate) is of interest for one dimensional histograms
+    pub y_axis: Axis,
+    /// Bin contents including the underflow (first) and overflow (last) bin
+    pub contents: Vec<f64>,
+    /// Sum of squares of weights per bin (same layout as `contents`); empty if not stored
+    pub sumw2: Vec<f64>,
+    /// Number of entries
+    pub entries: f64,
+    /// Statistics accumulated while filling
+    pub stats: Stats,
+}
+
+impl Histogram1D {
+    /// Number of bins (excluding under- and overflow)
+    pub fn nbins(&self) -> usize {
+        self.x_axis.nbins
+    }
+
+    /// Content of bin `bin`
+    pub fn bin_content(&self, bin: usize) -> f64 {
+        self.contents[bin]
+    }
+
+    /// Error of bin `bin`; the square root of the sum of squared
+    /// weights if available, else the square root of the content
+    pub fn bin_error(&self, bin: usize) -> f64 {
+        match self.sumw2.get(bin) {
+            Some(sumw2) => sumw2.sqrt(),
+            None => self.contents[bin].abs().sqrt(),
+        }
+    }
+
+    /// Contents of the bins within the axis range (i.e. without under- and overflow)
+    pub fn bins(&self) -> &[f64] {
+        &self.contents[1..=self.nbins()]
+    }
+
+    /// Content of the underflow bin
+    pub fn underflow(&self) -> f64 {
+        self.contents[0]
+    }
+
+    /// Content of the overflow bin
+    pub fn overflow(&self) -> f64 {
+        self.contents[self.nbins() + 1]
+    }
+
+    /// Mean of the filled values as computed by ROOT from the statistics
+    pub fn mean(&self) -> f64 {
+        self.stats.sumwx / self.stats.sumw
+    }
+}
+
+/// Parse the `TH1` base class of a histogram
+pub(crate) fn th1_base<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], TH1> {
+    let (i, ver) = be_u16(i)?;
+    let (i, tnamed) = length_value(checked_byte_count, tnamed)(i)?;
+    let (i, _tattline) = length_data(checked_byte_count)(i)?;
+    let (i, _tattfill) = length_data(checked_byte_count)(i)?;
+    let (i, _tattmarker) = length_data(checked_byte_count)(i)?;
+    let (i, _ncells) = be_i32(i)?;
+    let (i, x_axis) = length_value(checked_byte_count, |i| taxis(i, ctx))(i)?;
+    let (i, y_axis) = length_value(checked_byte_count, |i| taxis(i, ctx))(i)?;
+    let (i, _z_axis) = length_value(checked_byte_count, |i| taxis(i, ctx))(i)?;
+    let (i, _bar_offset) = be_i16(i)?;
+    let (i, _bar_width) = be_i16(i)?;
+    let (i, entries) = be_f64(i)?;
+    let (i, sumw) = be_f64(i)?;
+    let (i, sumw2_total) = be_f64(i)?;
+    let (i, sumwx) = be_f64(i)?;
+    let (i, sumwx2) = be_f64(i)?;
+    let (i, _maximum) = be_f64(i)?;
+    let (i, _minimum) = be_f64(i)?;
+    let (i, _norm_factor) = be_f64(i)?;
+    let (i, _contour) = tarray(be_f64, i)?;
+    let (i, sumw2) = tarray(be_f64, i)?;
+    let (i, _option) = string(i)?;
+    let (i, _functions) = raw(i, ctx)?;
+    let (i, buffer_size) = be_i32(i)?;
+    // `fBuffer` is a pointer to an array; a leading byte tells if it is set
+    let (i, has_buffer) = be_u8(i)?;
+    let (i, _buffer) = cond(has_buffer != 0, count(be_f64, buffer_size as usize))(i)?;
+    let (i, _bin_stat_err_opt) = cond(ver >= 7, be_i32)(i)?;
+    let (i, _stat_overflows) = cond(ver >= 8, be_i32)(i)?;
+    Ok((
+        i,
+        TH1 {
+            tnamed,
+            x_axis,
+            y_axis,
+            entries,
+            stats: Stats {
+                sumw,
+                sumw2: sumw2_total,
+                sumwx,
+                sumwx2,
+            },
+            sumw2,
+        },
+    ))
+}
+
+/// Parse the bin contents stored in the `TArray` of a histogram
+/// class, e.g. the `TArrayF` of a `TH1F`
+pub(crate) fn bin_contents<'s>(class_name: &str, i: &'s [u8]) -> IResult<&'s [u8], Vec<f64>> {
+    match class_name.chars().last() {
+        Some('F') => tarray(|i| map(be_f32, f64::from)(i), i),
+        Some('D') => tarray(be_f64, i),
+        Some('I') => tarray(|i| map(be_i32, f64::from)(i), i),
+        Some('S') => tarray(|i| map(be_i16, f64::from)(i), i),
+        Some('C') => tarray(|i| map(be_i8, f64::from)(i), i),
+        _ => Err(nom::Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Parse a one dimensional histogram of the given class (`TH1F`,
+/// `TH1D`, `TH1I` or `TH1S`). Usually used through `FileItem::as_th1`.
+pub fn th1<'s>(class_name: &str, i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Histogram1D> {
+    if !["TH1F", "TH1D", "TH1I", "TH1S"].contains(&class_name) {
+        return Err(nom::Err::Error(make_error(i, ErrorKind::Tag)));
+    }
+    let (i, _ver) = be_u16(i)?;
+    let (i, base) = length_value(checked_byte_count, |i| th1_base(i, ctx))(i)?;
+    let (i, contents) = bin_contents(class_name, i)?;
+    // The accessors rely on one content per bin plus under- and overflow
+    if contents.len() != base.x_axis.nbins + 2 {
+        return Err(nom::Err::Error(make_error(i, ErrorKind::LengthValue)));
+    }
+    Ok((
+        i,
+        Histogram1D {
+            name: base.tnamed.name,
+            title: base.tnamed.title,
+            class_name: class_name.to_string(),
+            x_axis: base.x_axis,
+            y_axis: base.y_axis,
+            contents,
+            sumw2: base.sumw2,
+            entries: base.entries,
+            stats: base.stats,
+        },
+    ))
+}
@@ -0,0 +1,115 @@
+use nom::{
+    error::{make_error, ErrorKind},
+    multi::length_value,
+    number::complete::*,
+    IResult,
+};
+
+use crate::core::*;
+use crate::hist_reader::axis::Axis;
+use crate::hist_reader::th1::{bin_contents, th1_base, Stats};
+
+/// A two dimensional histogram (`TH2F` or `TH2D`). Bins along each
+/// axis are numbered as for `Histogram1D`, i.e. including the
+/// under- (0) and overflow (`nbins + 1`) bins.
+#[derive(Debug, Clone)]
+pub struct Histogram2D {
+    /// Name of the histogram
+    pub name: String,
+    /// Title of the histogram
+    pub title: String,
+    /// ROOT class of the histogram (e.g. `TH2F`)
+    pub class_name: String,
+    /// Binning and label of the first dimension
+    pub x_axis: Axis,
+    /// Binning and label of the second dimension
+    pub y_axis: Axis,
+    /// Bin contents in ROOT's global bin order (x running fastest),
+    /// including under- and overflow bins in both dimensions
+    pub contents: Vec<f64>,
+    /// Sum of squares of weights per bin (same layout as `contents`); empty if not stored
+    pub sumw2: Vec<f64>,
+    /// Number of entries
+    pub entries: f64,
+    /// Statistics of the first dimension accumulated while filling
+    pub stats: Stats,
+    /// Total sum of weight*y
+    pub sumwy: f64,
+    /// Total sum of weight*y*y
+    pub sumwy2: f64,
+    /// Total sum of weight*x*y
+    pub sumwxy: f64,
+}
+
+impl Histogram2D {
+    /// Number of bins along x (excluding under- and overflow)
+    pub fn nbins_x(&self) -> usize {
+        self.x_axis.nbins
+    }
+
+    /// Number of bins along y (excluding under- and overflow)
+    pub fn nbins_y(&self) -> usize {
+        self.y_axis.nbins
+    }
+
+    /// The global bin number (index into `contents`) of bin (`ix`, `iy`)
+    pub fn bin(&self, ix: usize, iy: usize) -> usize {
+        ix + (self.nbins_x() + 2) * iy
+    }
+
+    /// Content of bin (`ix`, `iy`)
+    pub fn bin_content(&self, ix: usize, iy: usize) -> f64 {
+        self.contents[self.bin(ix, iy)]
+    }
+
+    /// Error of bin (`ix`, `iy`); the square root of the sum of
+    /// squared weights if available, else the square root of the content
+    pub fn bin_error(&self, ix: usize, iy: usize) -> f64 {
+        let bin = self.bin(ix, iy);
+        match self.sumw2.get(bin) {
+            Some(sumw2) => sumw2.sqrt(),
+            None => self.contents[bin].abs().sqrt(),
+        }
+    }
+
+    /// The bin (`ix`, `iy`) the point (`x`, `y`) falls into
+    pub fn find_bin(&self, x: f64, y: f64) -> (usize, usize) {
+        (self.x_axis.find_bin(x), self.y_axis.find_bin(y))
+    }
+}
+
+/// Parse a two dimensional histogram of the given class (`TH2F` or
+/// `TH2D`). Usually used through `FileItem::as_th2`.
+pub fn th2<'s>(class_name: &str, i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Histogram2D> {
+    if !["TH2F", "TH2D"].contains(&class_name) {
+        return Err(nom::Err::Error(make_error(i, ErrorKind::Tag)));
+    }
+    let (i, _ver) = be_u16(i)?;
+    let (i, (base, sumwy, sumwy2, sumwxy)) = length_value(checked_byte_count, |i| {
+        let (i, _ver) = be_u16(i)?;
+        let (i, base) = length_value(checked_byte_count, |i| th1_base(i, ctx))(i)?;
+        let (i, _scale_factor) = be_f64(i)?;
+        let (i, sumwy) = be_f64(i)?;
+        let (i, sumwy2) = be_f64(i)?;
+        let (i, sumwxy) = be_f64(i)?;
+        Ok((i, (base, sumwy, sumwy2, sumwxy)))
+    })(i)?;
+    let (i, contents) = bin_contents(class_name, i)?;
+    Ok((
+        i,
+        Histogram2D {
+            name: base.tnamed.name,
+            title: base.tnamed.title,
+            class_name: class_name.to_string(),
+            x_axis: base.x_axis,
+            y_axis: base.y_axis,
+            contents,
+            sumw2: base.sumw2,
+            entries: base.entries,
+            stats: base.stats,
+            sumwy,
+            sumwy2,
+            sumwxy,
+        },
+    ))
+}
@@ -0,0 +1,152 @@
+use nom::{
+    combinator::{cond, map},
+    multi::length_value,
+    number::complete::*,
+    IResult,
+};
+
+use crate::core::*;
+use crate::hist_reader::axis::Axis;
+use crate::hist_reader::th1::{bin_contents, th1_base};
+
+/// How the error of a profile bin is computed; mirrors the options
+/// of `TProfile::SetErrorOption`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Error of the mean (default, option "")
+    Mean,
+    /// Spread of the values (option "s")
+    Spread,
+    /// Like `Mean`, but for integer valued data (option "i")
+    SpreadI,
+    /// `1/sqrt(sum of weights)` (option "g")
+    SpreadG,
+}
+
+/// A profile histogram (`TProfile`), storing the mean of a quantity
+/// `y` per bin of `x`. Bins are numbered as for `Histogram1D`.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Name of the profile
+    pub name: String,
+    /// Title of the profile
+    pub title: String,
+    /// Binning and label of the abscissa
+    pub x_axis: Axis,
+    /// Only the title (the label of the ordinate) is of interest for profiles
+    pub y_axis: Axis,
+    /// Sum of weight*y per bin including under- and overflow
+    pub sumwy: Vec<f64>,
+    /// Sum of weight*y*y per bin
+    pub sumwy2: Vec<f64>,
+    /// Sum of weights per bin
+    pub bin_entries: Vec<f64>,
+    /// Sum of squares of weights per bin; empty if not stored
+    pub bin_sumw2: Vec<f64>,
+    /// How bin errors are computed
+    pub error_mode: ErrorMode,
+    /// Lower limit of accepted y values (no limit if equal to `ymax`)
+    pub ymin: f64,
+    /// Upper limit of accepted y values
+    pub ymax: f64,
+    /// Number of entries
+    pub entries: f64,
+}
+
+impl Profile {
+    /// Number of bins (excluding under- and overflow)
+    pub fn nbins(&self) -> usize {
+        self.x_axis.nbins
+    }
+
+    /// Effective number of entries in bin `bin`
+    pub fn bin_effective_entries(&self, bin: usize) -> f64 {
+        let sumw = self.bin_entries[bin];
+        match self.bin_sumw2.get(bin) {
+            Some(&sumw2) if sumw2 > 0.0 => sumw * sumw / sumw2,
+            _ => sumw,
+        }
+    }
+
+    /// Mean of y in bin `bin`; 0 for empty bins
+    pub fn bin_mean(&self, bin: usize) -> f64 {
+        let sumw = self.bin_entries[bin];
+        if sumw == 0.0 {
+            0.0
+        } else {
+            self.sumwy[bin] / sumw
+        }
+    }
+
+    /// Error of bin `bin` according to `error_mode`, following `TProfile::GetBinError`
+    pub fn bin_error(&self, bin: usize) -> f64 {
+        let sumw = self.bin_entries[bin];
+        if sumw == 0.0 {
+            return 0.0;
+        }
+        let mean = self.sumwy[bin] / sumw;
+        let spread = (self.sumwy2[bin] / sumw - mean * mean).abs().sqrt();
+        let neff = self.bin_effective_entries(bin);
+        match self.error_mode {
+            ErrorMode::Mean => spread / neff.sqrt(),
+            ErrorMode::Spread => spread,
+            ErrorMode::SpreadI if spread != 0.0 => spread / neff.sqrt(),
+            ErrorMode::SpreadI => 1.0 / (12.0 * neff).sqrt(),
+            ErrorMode::SpreadG => 1.0 / sumw.sqrt(),
+        }
+    }
+
+    /// Means of all bins within the axis range (i.e. without under- and overflow)
+    pub fn means(&self) -> Vec<f64> {
+        (1..=self.nbins()).map(|bin| self.bin_mean(bin)).collect()
+    }
+
+    /// Errors of all bins within the axis range (i.e. without under- and overflow)
+    pub fn errors(&self) -> Vec<f64> {
+        (1..=self.nbins()).map(|bin| self.bin_error(bin)).collect()
+    }
+}
+
+fn error_mode(i: &[u8]) -> IResult<&[u8], ErrorMode> {
+    map(be_i32, |mode| match mode {
+        1 => ErrorMode::Spread,
+        2 => ErrorMode::SpreadI,
+        3 => ErrorMode::SpreadG,
+        _ => ErrorMode::Mean,
+    })(i)
+}
+
+/// Parse a `TProfile`. Usually used through `FileItem::as_profile`.
+pub fn tprofile<'s>(i: &'s [u8], ctx: &'s Context) -> IResult<&'s [u8], Profile> {
+    let (i, ver) = be_u16(i)?;
+    let (i, (base, sumwy)) = length_value(checked_byte_count, |i| {
+        let (i, _ver) = be_u16(i)?;
+        let (i, base) = length_value(checked_byte_count, |i| th1_base(i, ctx))(i)?;
+        let (i, sumwy) = bin_contents("TH1D", i)?;
+        Ok((i, (base, sumwy)))
+    })(i)?;
+    let (i, bin_entries) = tarray(be_f64, i)?;
+    let (i, error_mode) = error_mode(i)?;
+    let (i, ymin) = be_f64(i)?;
+    let (i, ymax) = be_f64(i)?;
+    let (i, _tsumwy) = be_f64(i)?;
+    let (i, _tsumwy2) = be_f64(i)?;
+    let (i, bin_sumw2) = cond(ver >= 7, |i| tarray(be_f64, i))(i)?;
+    Ok((
+        i,
+        Profile {
+            name: base.tnamed.name,
+            title: base.tnamed.title,
+            x_axis: base.x_axis,
+            y_axis: base.y_axis,
+            sumwy,
+            sumwy2: base.sumw2,
+            bin_entries,
+            bin_sumw2: bin_sumw2.unwrap_or_default(),
+            error_mode,
+            ymin,
+            ymax,
+            entries: base.entries,
+        },
+    ))
+}
@@ -31,6 +31,7 @@ extern crate alice_open_data;
 // pub mod core_types;
 mod code_gen;
 pub mod core;
+pub mod hist_reader;
 pub mod test_utils;
 mod tests;
 pub mod tree_reader;
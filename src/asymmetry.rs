@@ -0,0 +1,197 @@
+use serde::Serialize;
+
+use crate::error::AnalysisError;
+use crate::grouping::DetectorGroup;
+use crate::models::MusrRootFile;
+use crate::packing::{packed_bin, Packing};
+
+// Forward / backward asymmetry
+//  A(t) = (F(t) - alpha B(t)) / (F(t) + alpha B(t))
+// where F and B are the background corrected sums of the detectors of the forward and backward group.
+// alpha corrects for the different efficiencies of the groups. beta corrects for different asymmetries of
+// the groups (F ~ 1 + A, B ~ 1 - beta A); with beta the asymmetry becomes
+//  A = 2 a / ((1 + beta) - a (1 - beta))
+// where a is the asymmetry without beta correction.
+//
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/user-manual.html#asymmetry-fit-type-2
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Asymmetry {
+    pub time: Vec<f64>, // in µs relative to t0
+    pub asymmetry: Vec<f64>,
+    pub error: Vec<f64>,
+}
+
+impl MusrRootFile {
+    pub fn asymmetry(
+        &self,
+        forward: &DetectorGroup,
+        backward: &DetectorGroup,
+        alpha: f64,
+        beta: Option<f64>,
+    ) -> Result<Asymmetry, AnalysisError> {
+        self.packed_asymmetry(forward, backward, alpha, beta, &Packing::Fixed(1))
+    }
+
+    // Asymmetry of the packed forward and backward sums, which is less biased at low statistics than
+    // packing the asymmetry itself
+    pub fn packed_asymmetry(
+        &self,
+        forward: &DetectorGroup,
+        backward: &DetectorGroup,
+        alpha: f64,
+        beta: Option<f64>,
+        packing: &Packing,
+    ) -> Result<Asymmetry, AnalysisError> {
+        let forward_sum = self.group(forward)?;
+        let backward_sum = self.group(backward)?;
+
+        // Bin i of the forward sum corresponds to bin i + shift of the backward sum
+        let shift = (backward_sum.time_zero_bin - forward_sum.time_zero_bin).round() as i64;
+        let first = forward_sum
+            .first_good_bin
+            .max(backward_sum.first_good_bin - shift);
+        let last = forward_sum
+            .last_good_bin
+            .min(backward_sum.last_good_bin - shift);
+        if first > last {
+            return Err(AnalysisError::EmptyWindow);
+        }
+
+        let f = &forward_sum.contents[first as usize..=last as usize];
+        let b = &backward_sum.contents[(first + shift) as usize..=(last + shift) as usize];
+        let times: Vec<f64> = (first..=last)
+            .map(|bin| forward_sum.time(bin as usize))
+            .collect();
+
+        let beta = beta.unwrap_or(1.0);
+        let mut asymmetry = Asymmetry {
+            time: vec![],
+            asymmetry: vec![],
+            error: vec![],
+        };
+        for range in packing.ranges(&times) {
+            let (f, f_variance) = packed_bin(&f[range.clone()], forward.background);
+            let (b, b_variance) = packed_bin(&b[range.clone()], backward.background);
+            let (a, error) = bin_asymmetry(f, f_variance, b, b_variance, alpha, beta);
+            asymmetry
+                .time
+                .push((times[range.start] + times[range.end - 1]) / 2.0);
+            asymmetry.asymmetry.push(a);
+            asymmetry.error.push(error);
+        }
+        Ok(asymmetry)
+    }
+}
+
+// Asymmetry and its error of a single bin from the background corrected counts and their variances
+fn bin_asymmetry(
+    f: f64,
+    f_variance: f64,
+    b: f64,
+    b_variance: f64,
+    alpha: f64,
+    beta: f64,
+) -> (f64, f64) {
+    let denominator = f + alpha * b;
+    if denominator == 0.0 {
+        return (0.0, 1.0);
+    }
+    let a = (f - alpha * b) / denominator;
+    let error = 2.0 * alpha * (b.powi(2) * f_variance + f.powi(2) * b_variance).sqrt()
+        / denominator.powi(2);
+    if beta == 1.0 {
+        return (a, error);
+    }
+    let beta_denominator = (1.0 + beta) - a * (1.0 - beta);
+    let corrected = 2.0 * a / beta_denominator;
+    let derivative = 2.0 * (1.0 + beta) / beta_denominator.powi(2);
+    (corrected, derivative * error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::{Background, BackgroundEstimate};
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    #[test]
+    fn single_bin() {
+        let (a, error) = bin_asymmetry(300.0, 300.0, 100.0, 100.0, 1.0, 1.0);
+        assert_eq!(a, 0.5);
+        assert!((error - 2.0 * (1e4 * 300.0 + 9e4 * 100.0f64).sqrt() / 16e4).abs() < 1e-15);
+
+        // alpha compensates different efficiencies
+        assert_eq!(bin_asymmetry(200.0, 200.0, 100.0, 100.0, 2.0, 1.0).0, 0.0);
+
+        // F ~ 1 + A, B ~ 1 - beta A with A = 0.2 and beta = 0.5
+        let (a, _) = bin_asymmetry(1.2, 1.2, 0.9, 0.9, 1.0, 0.5);
+        assert!((a - 0.2).abs() < 1e-12);
+
+        assert_eq!(bin_asymmetry(0.0, 0.0, 0.0, 0.0, 1.0, 1.0), (0.0, 1.0));
+    }
+
+    #[tokio::test]
+    async fn lem_asymmetry() {
+        let musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+
+        // Left / right detectors of the downstream and upstream rings, electric field off
+        let forward = DetectorGroup::new("forward", &[1, 5]);
+        let backward = DetectorGroup::new("backward", &[3, 7]);
+        let asymmetry = musr_root_file
+            .asymmetry(&forward, &backward, 1.0, None)
+            .unwrap();
+        assert_eq!(asymmetry.time.len(), 66600 - 2834 + 1);
+        assert_eq!(asymmetry.time[0], 0.0);
+        assert_eq!(asymmetry.asymmetry.len(), asymmetry.error.len());
+        assert!(asymmetry.asymmetry.iter().all(|a| a.abs() <= 1.0));
+        assert!(asymmetry.error.iter().all(|error| *error > 0.0));
+
+        // Background from the bins before t0 of every histogram of a group
+        let detector_info = &musr_root_file.run_header.detector_info;
+        let h_decay = &musr_root_file.histos.decay_ana_module.h_decay;
+        let group_background = |numbers: &[i64]| {
+            h_decay
+                .iter()
+                .filter(|h| numbers.contains(&h.number))
+                .map(|h| {
+                    let detector = h.detector(detector_info).unwrap();
+                    h.background(detector, &Background::PreT0).unwrap()
+                })
+                .fold(BackgroundEstimate::default(), |sum, estimate| {
+                    sum + estimate
+                })
+        };
+        let forward = DetectorGroup {
+            background: group_background(&[1, 5]),
+            ..forward
+        };
+        let backward = DetectorGroup {
+            background: group_background(&[3, 7]),
+            ..backward
+        };
+        assert!(forward.background.value > 0.0 && forward.background.error > 0.0);
+        let corrected = musr_root_file
+            .asymmetry(&forward, &backward, 1.0, None)
+            .unwrap();
+        assert_eq!(corrected.time, asymmetry.time);
+        assert!(corrected.error[0] >= asymmetry.error[0]);
+
+        let packed = musr_root_file
+            .packed_asymmetry(&forward, &backward, 1.0, None, &Packing::Fixed(100))
+            .unwrap();
+        assert_eq!(packed.time.len(), asymmetry.time.len() / 100);
+        assert!((packed.time[0] - 49.5 * 0.1953125e-3).abs() < 1e-12);
+        assert!(packed.error[0] < corrected.error[0]);
+
+        assert_eq!(
+            musr_root_file.asymmetry(&DetectorGroup::new("forward", &[9]), &backward, 1.0, None),
+            Err(AnalysisError::MissingHistogram(9))
+        );
+        assert_eq!(
+            musr_root_file.asymmetry(&DetectorGroup::new("forward", &[]), &backward, 1.0, None),
+            Err(AnalysisError::EmptyGroup)
+        );
+    }
+}
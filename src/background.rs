@@ -0,0 +1,155 @@
+use std::ops::{Add, RangeInclusive};
+
+use serde::Serialize;
+
+use crate::error::AnalysisError;
+use crate::models::{Detector, HDecay};
+
+// Constant background of a decay histogram, i.e. counts per bin not coming from muon decays
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    PreT0,                        // mean of the bins 0.1 t0 ..= 0.6 t0, as estimated by musrfit
+    Range(RangeInclusive<usize>), // mean of an explicit bin range
+    Fixed(f64),                   // known value without uncertainty
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct BackgroundEstimate {
+    pub value: f64, // counts per bin
+    pub error: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackgroundCorrected {
+    pub counts: Vec<f64>,
+    pub errors: Vec<f64>, // Poisson errors of the raw counts combined with the error of the background
+    pub background: BackgroundEstimate,
+}
+
+// Background of the sum of two histograms, e.g. of a detector group
+impl Add for BackgroundEstimate {
+    type Output = BackgroundEstimate;
+
+    fn add(self, other: BackgroundEstimate) -> BackgroundEstimate {
+        BackgroundEstimate {
+            value: self.value + other.value,
+            error: self.error.hypot(other.error),
+        }
+    }
+}
+
+impl BackgroundEstimate {
+    pub fn fixed(value: f64) -> BackgroundEstimate {
+        BackgroundEstimate { value, error: 0.0 }
+    }
+}
+
+impl HDecay {
+    pub fn background(
+        &self,
+        detector: &Detector,
+        background: &Background,
+    ) -> Result<BackgroundEstimate, AnalysisError> {
+        estimate(&self.contents, detector.t0_bin()?, background)
+    }
+
+    pub fn background_corrected(&self, background: BackgroundEstimate) -> BackgroundCorrected {
+        let counts = self
+            .contents
+            .iter()
+            .map(|count| count - background.value)
+            .collect();
+        // As in musrfit an empty bin is given an error of 1 count
+        let errors = self
+            .contents
+            .iter()
+            .map(|count| (count.max(1.0) + background.error.powi(2)).sqrt())
+            .collect();
+
+        BackgroundCorrected {
+            counts,
+            errors,
+            background,
+        }
+    }
+}
+
+pub(crate) fn estimate(
+    contents: &[f64],
+    time_zero_bin: f64,
+    background: &Background,
+) -> Result<BackgroundEstimate, AnalysisError> {
+    let range = match background {
+        Background::Fixed(value) => return Ok(BackgroundEstimate::fixed(*value)),
+        Background::Range(range) => range.clone(),
+        Background::PreT0 => {
+            (0.1 * time_zero_bin).round() as usize..=(0.6 * time_zero_bin).round() as usize
+        }
+    };
+    let bins = contents
+        .get(range.clone())
+        .filter(|bins| !bins.is_empty())
+        .ok_or(AnalysisError::InvalidBackgroundRange(
+            *range.start(),
+            *range.end(),
+        ))?;
+
+    // Mean of Poisson distributed counts
+    let n = bins.len() as f64;
+    let sum: f64 = bins.iter().sum();
+    Ok(BackgroundEstimate {
+        value: sum / n,
+        error: sum.sqrt() / n,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_fixtures::{detector, h_decay};
+
+    #[test]
+    fn estimators() {
+        // Background of 4 counts before t0 at bin 10
+        let mut contents = vec![4.0; 10];
+        contents[0] = 100.0;
+        contents.extend([500.0, 300.0, 200.0]);
+        let h_decay = h_decay(contents);
+        let left = detector(10.0, 10, 12);
+
+        // Bins 1 ..= 6
+        let estimate = h_decay.background(&left, &Background::PreT0).unwrap();
+        assert_eq!(estimate.value, 4.0);
+        assert_eq!(estimate.error, 24f64.sqrt() / 6.0);
+
+        let estimate = h_decay
+            .background(&left, &Background::Range(0..=1))
+            .unwrap();
+        assert_eq!(estimate.value, 52.0);
+
+        let estimate = h_decay.background(&left, &Background::Fixed(3.5)).unwrap();
+        assert_eq!(estimate, BackgroundEstimate::fixed(3.5));
+
+        assert_eq!(
+            h_decay.background(&left, &Background::Range(12..=20)),
+            Err(AnalysisError::InvalidBackgroundRange(12, 20))
+        );
+    }
+
+    #[test]
+    fn correction() {
+        let h_decay = h_decay(vec![4.0, 0.0, 104.0]);
+        let background = BackgroundEstimate {
+            value: 4.0,
+            error: 0.5,
+        };
+        let corrected = h_decay.background_corrected(background);
+        assert_eq!(corrected.counts, [0.0, -4.0, 100.0]);
+        assert_eq!(corrected.errors[0], 4.25f64.sqrt());
+        assert_eq!(corrected.errors[1], 1.25f64.sqrt());
+
+        let sum = background + BackgroundEstimate::fixed(1.0);
+        assert_eq!(sum.value, 5.0);
+        assert_eq!(sum.error, 0.5);
+    }
+}
@@ -24,3 +24,51 @@ impl From<io::Error> for ParsingError {
         ParsingError::IoError(error)
     }
 }
+
+impl From<failure::Error> for ParsingError {
+    fn from(error: failure::Error) -> Self {
+        ParsingError::ParseError(error.to_string())
+    }
+}
+
+// Errors of the analysis of decay histograms, e.g. an asymmetry of detectors missing in the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisError {
+    MissingHistogram(i64),   // no hDecay histogram with this number
+    MissingDetector(i64),    // no detector for the hDecay histogram with this number
+    MissingTimeZeroBin(i64), // the detector with this Histo Number has no Time Zero Bin
+    EmptyGroup,
+    EmptyWindow,                          // the good-bin windows do not overlap
+    InvalidBackgroundRange(usize, usize), // first and last bin of a range outside of the histogram or empty
+    FitFailed(String),
+    InvalidSpectrum(String), // data which cannot be Fourier transformed, e.g. with variable packing
+}
+
+impl Error for AnalysisError {}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::MissingHistogram(number) => {
+                write!(f, "Histogram hDecay{:03} not found", number)
+            }
+            AnalysisError::MissingDetector(number) => {
+                write!(f, "No detector found for histogram hDecay{:03}", number)
+            }
+            AnalysisError::MissingTimeZeroBin(number) => {
+                write!(
+                    f,
+                    "Detector with Histo Number {} has no Time Zero Bin",
+                    number
+                )
+            }
+            AnalysisError::EmptyGroup => write!(f, "Detector group contains no histograms"),
+            AnalysisError::EmptyWindow => write!(f, "Good-bin windows do not overlap"),
+            AnalysisError::InvalidBackgroundRange(first, last) => {
+                write!(f, "Invalid background range {}..={}", first, last)
+            }
+            AnalysisError::FitFailed(msg) => write!(f, "Fit failed: {}", msg),
+            AnalysisError::InvalidSpectrum(msg) => write!(f, "Invalid spectrum: {}", msg),
+        }
+    }
+}
@@ -0,0 +1,726 @@
+use serde::Serialize;
+
+use crate::asymmetry::Asymmetry;
+use crate::error::AnalysisError;
+use crate::packing::PackedHistogram;
+use crate::theory::Theory;
+
+const MAX_ITERATIONS: usize = 1000;
+const TOLERANCE: f64 = 1e-10; // relative change of the objective at convergence
+
+// Fit parameter as in the FITPARAMETER block of musrfit. Parameters are shared by using the same index in
+// several theory functions or runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    pub value: f64,
+    pub step: f64, // expected scale of the parameter, used for numerical derivatives
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+    pub fixed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FitType {
+    Asymmetry, // the theory is fitted directly to the data
+    // Asymmetry (F - B) / (F + B) calculated without alpha, the alpha parameter with index `alpha` is fitted
+    // through the theory ((alpha - 1) + (alpha + 1) A) / ((alpha + 1) + (alpha - 1) A)
+    RawAsymmetry {
+        alpha: usize,
+    },
+    // N(t) = N0 exp(-t / tau) (1 + theory) + background per bin, N0 and background are parameter indices
+    // and tau is in µs
+    SingleHistogram {
+        n0: usize,
+        background: usize,
+        tau: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Estimator {
+    ChiSquare,
+    // Poisson log-likelihood ratio 2 sum(f - y + y ln(y / f)) of Baker and Cousins, only for single histograms
+    PoissonLikelihood,
+}
+
+// Data points of one run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FitData {
+    pub time: Vec<f64>, // in µs relative to t0
+    pub value: Vec<f64>,
+    pub error: Vec<f64>,
+    pub bins: Vec<usize>, // number of packed bins per point
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitRun {
+    pub data: FitData,
+    pub theory: Theory,
+    pub fit_type: FitType,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FitResult {
+    pub parameters: Vec<Parameter>, // with the fitted values
+    pub errors: Vec<f64>,           // 0 for fixed parameters
+    pub covariance: Vec<Vec<f64>>,
+    pub objective: f64, // chi-square or likelihood ratio at the minimum
+    pub ndf: usize,
+    pub converged: bool,
+    pub iterations: usize,
+}
+
+impl Parameter {
+    pub fn new(name: &str, value: f64) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            value,
+            step: if value == 0.0 { 0.1 } else { 0.1 * value.abs() },
+            lower: None,
+            upper: None,
+            fixed: false,
+        }
+    }
+
+    pub fn bounded(name: &str, value: f64, lower: f64, upper: f64) -> Parameter {
+        Parameter {
+            lower: Some(lower),
+            upper: Some(upper),
+            ..Parameter::new(name, value)
+        }
+    }
+
+    pub fn fixed(name: &str, value: f64) -> Parameter {
+        Parameter {
+            fixed: true,
+            ..Parameter::new(name, value)
+        }
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        let value = self.lower.map_or(value, |lower| value.max(lower));
+        self.upper.map_or(value, |upper| value.min(upper))
+    }
+}
+
+impl FitType {
+    // Indices of the parameters used by the fit type itself, e.g. N0 and background
+    pub fn parameters(&self) -> Vec<usize> {
+        match *self {
+            FitType::Asymmetry => vec![],
+            FitType::RawAsymmetry { alpha } => vec![alpha],
+            FitType::SingleHistogram { n0, background, .. } => vec![n0, background],
+        }
+    }
+
+    // The same fit type with parameter `i` replaced by `map[i]`
+    pub fn remap(&self, map: &[usize]) -> FitType {
+        match *self {
+            FitType::Asymmetry => FitType::Asymmetry,
+            FitType::RawAsymmetry { alpha } => FitType::RawAsymmetry { alpha: map[alpha] },
+            FitType::SingleHistogram {
+                n0,
+                background,
+                tau,
+            } => FitType::SingleHistogram {
+                n0: map[n0],
+                background: map[background],
+                tau,
+            },
+        }
+    }
+}
+
+impl From<&Asymmetry> for FitData {
+    fn from(asymmetry: &Asymmetry) -> FitData {
+        FitData {
+            time: asymmetry.time.clone(),
+            value: asymmetry.asymmetry.clone(),
+            error: asymmetry.error.clone(),
+            bins: vec![1; asymmetry.time.len()],
+        }
+    }
+}
+
+impl From<&PackedHistogram> for FitData {
+    fn from(histogram: &PackedHistogram) -> FitData {
+        FitData {
+            time: histogram.time.clone(),
+            value: histogram.counts.clone(),
+            error: histogram.errors.clone(),
+            bins: histogram.bins.clone(),
+        }
+    }
+}
+
+impl FitData {
+    // Points with times from `start` to `end` in µs, the fit range
+    pub fn restrict(&self, start: f64, end: f64) -> FitData {
+        let keep: Vec<usize> = (0..self.time.len())
+            .filter(|i| self.time[*i] >= start && self.time[*i] <= end)
+            .collect();
+        FitData {
+            time: keep.iter().map(|i| self.time[*i]).collect(),
+            value: keep.iter().map(|i| self.value[*i]).collect(),
+            error: keep.iter().map(|i| self.error[*i]).collect(),
+            bins: keep.iter().map(|i| self.bins[*i]).collect(),
+        }
+    }
+}
+
+impl FitRun {
+    pub fn new(data: FitData, theory: Theory, fit_type: FitType) -> FitRun {
+        FitRun {
+            data,
+            theory,
+            fit_type,
+        }
+    }
+
+    // Expected value of every data point
+    pub fn model(&self, parameters: &[f64]) -> Vec<f64> {
+        let theory = self.theory.evaluate(&self.data.time, parameters);
+        match self.fit_type {
+            FitType::Asymmetry => theory,
+            FitType::RawAsymmetry { alpha } => {
+                let alpha = parameters[alpha];
+                theory
+                    .iter()
+                    .map(|a| {
+                        ((alpha - 1.0) + (alpha + 1.0) * a) / ((alpha + 1.0) + (alpha - 1.0) * a)
+                    })
+                    .collect()
+            }
+            FitType::SingleHistogram {
+                n0,
+                background,
+                tau,
+            } => theory
+                .iter()
+                .zip(&self.data.time)
+                .zip(&self.data.bins)
+                .map(|((theory, t), bins)| {
+                    *bins as f64
+                        * (parameters[n0] * (-t / tau).exp() * (1.0 + theory)
+                            + parameters[background])
+                })
+                .collect(),
+        }
+    }
+
+    fn parameters(&self) -> Vec<usize> {
+        let mut parameters = self.theory.parameters();
+        parameters.extend(self.fit_type.parameters());
+        parameters
+    }
+
+    // Residuals whose sum of squares is the objective. For the likelihood these are the signed deviance
+    // residuals, so that the same minimizer serves both estimators.
+    fn residuals(&self, parameters: &[f64], estimator: Estimator, residuals: &mut Vec<f64>) {
+        let model = self.model(parameters);
+        let data = &self.data;
+        for (i, f) in model.iter().enumerate() {
+            let y = data.value[i];
+            residuals.push(match estimator {
+                Estimator::ChiSquare if data.error[i] > 0.0 => (y - f) / data.error[i],
+                Estimator::ChiSquare => 0.0,
+                Estimator::PoissonLikelihood if *f <= 0.0 => f64::INFINITY,
+                Estimator::PoissonLikelihood => {
+                    let deviance = if y > 0.0 {
+                        f - y + y * (y / f).ln()
+                    } else {
+                        *f
+                    };
+                    (y - f).signum() * (2.0 * deviance.max(0.0)).sqrt()
+                }
+            });
+        }
+    }
+}
+
+// Fit the theories of all runs to their data by minimizing chi-square or the Poisson likelihood ratio with
+// the Levenberg-Marquardt algorithm. Parameters outside of their bounds are set to the bound. Errors are
+// taken from the Hessian H of the objective at the minimum, i.e. the covariance matrix is 2 H^-1.
+pub fn fit(
+    parameters: &[Parameter],
+    runs: &[FitRun],
+    estimator: Estimator,
+) -> Result<FitResult, AnalysisError> {
+    if let Some(index) = runs
+        .iter()
+        .flat_map(FitRun::parameters)
+        .find(|index| *index >= parameters.len())
+    {
+        return Err(AnalysisError::FitFailed(format!(
+            "Parameter {} is not defined",
+            index + 1
+        )));
+    }
+    if estimator == Estimator::PoissonLikelihood
+        && runs
+            .iter()
+            .any(|run| !matches!(run.fit_type, FitType::SingleHistogram { .. }))
+    {
+        return Err(AnalysisError::FitFailed(
+            "Poisson likelihood needs single histogram data".into(),
+        ));
+    }
+    let free: Vec<usize> = (0..parameters.len())
+        .filter(|i| !parameters[*i].fixed)
+        .collect();
+    // A free parameter without influence on the model would make the Hessian singular
+    let used: Vec<usize> = runs.iter().flat_map(FitRun::parameters).collect();
+    if let Some(index) = free.iter().find(|index| !used.contains(index)) {
+        return Err(AnalysisError::FitFailed(format!(
+            "Free parameter {} is not used",
+            index + 1
+        )));
+    }
+    let points: usize = runs.iter().map(|run| run.data.time.len()).sum();
+    if points <= free.len() {
+        return Err(AnalysisError::FitFailed(format!(
+            "{} points for {} free parameters",
+            points,
+            free.len()
+        )));
+    }
+
+    let residuals = |values: &[f64]| {
+        let mut residuals = Vec::with_capacity(points);
+        for run in runs {
+            run.residuals(values, estimator, &mut residuals);
+        }
+        residuals
+    };
+    let objective = |residuals: &[f64]| residuals.iter().map(|r| r * r).sum::<f64>();
+
+    let mut values: Vec<f64> = parameters.iter().map(|p| p.clamp(p.value)).collect();
+    let mut current = residuals(&values);
+    let mut chi2 = objective(&current);
+    if !chi2.is_finite() {
+        return Err(AnalysisError::FitFailed(
+            "Objective not finite at the start values".into(),
+        ));
+    }
+
+    let mut lambda = 1e-3;
+    let mut converged = false;
+    let mut stalled = false;
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS && !converged && !stalled {
+        iterations += 1;
+        // Numerical Jacobian of the residuals with respect to the free parameters
+        let jacobian: Vec<Vec<f64>> = free
+            .iter()
+            .map(|p| {
+                let h = 1e-4 * parameters[*p].step.abs().max(1e-12);
+                let mut shifted = values.clone();
+                shifted[*p] += h;
+                residuals(&shifted)
+                    .iter()
+                    .zip(&current)
+                    .map(|(r, r0)| (r - r0) / h)
+                    .collect()
+            })
+            .collect();
+        let n = free.len();
+        let mut normal = vec![vec![0.0; n]; n];
+        let mut gradient = vec![0.0; n];
+        for a in 0..n {
+            for b in 0..=a {
+                let sum: f64 = jacobian[a]
+                    .iter()
+                    .zip(&jacobian[b])
+                    .map(|(x, y)| x * y)
+                    .sum();
+                normal[a][b] = sum;
+                normal[b][a] = sum;
+            }
+            gradient[a] = -jacobian[a]
+                .iter()
+                .zip(&current)
+                .map(|(j, r)| j * r)
+                .sum::<f64>();
+        }
+
+        // Increase the damping until a step lowers the objective
+        loop {
+            let mut damped = normal.clone();
+            for (a, row) in damped.iter_mut().enumerate() {
+                row[a] += lambda * normal[a][a].max(1e-12);
+            }
+            let step = match invert(damped) {
+                Some(inverse) => inverse
+                    .iter()
+                    .map(|row| row.iter().zip(&gradient).map(|(x, g)| x * g).sum::<f64>())
+                    .collect::<Vec<f64>>(),
+                None => vec![0.0; n],
+            };
+            let mut trial = values.clone();
+            for (a, p) in free.iter().enumerate() {
+                trial[*p] = parameters[*p].clamp(values[*p] + step[a]);
+            }
+            let trial_residuals = residuals(&trial);
+            let trial_chi2 = objective(&trial_residuals);
+            if trial_chi2.is_finite() && trial_chi2 <= chi2 {
+                converged = chi2 - trial_chi2 <= TOLERANCE * (chi2 + 1.0);
+                values = trial;
+                current = trial_residuals;
+                chi2 = trial_chi2;
+                lambda = (lambda / 10.0).max(1e-12);
+                break;
+            }
+            lambda *= 10.0;
+            if lambda > 1e12 {
+                // No step lowers the objective any more. This is the minimum within precision only if the
+                // decrease expected from the gradient is negligible, otherwise the fit is stuck.
+                let expected: f64 = gradient
+                    .iter()
+                    .enumerate()
+                    .map(|(a, g)| g * g / normal[a][a].max(1e-300))
+                    .sum();
+                converged = expected <= TOLERANCE.sqrt() * (chi2 + 1.0);
+                stalled = true;
+                break;
+            }
+        }
+    }
+
+    // Hessian of the objective with central differences
+    let n = free.len();
+    let shift = |offsets: &[(usize, f64)]| {
+        let mut shifted = values.clone();
+        for (p, h) in offsets {
+            shifted[*p] += h;
+        }
+        objective(&residuals(&shifted))
+    };
+    let steps: Vec<f64> = free
+        .iter()
+        .map(|p| 1e-2 * parameters[*p].step.abs().max(1e-12))
+        .collect();
+    let mut hessian = vec![vec![0.0; n]; n];
+    for a in 0..n {
+        let (pa, ha) = (free[a], steps[a]);
+        hessian[a][a] = (shift(&[(pa, ha)]) - 2.0 * chi2 + shift(&[(pa, -ha)])) / (ha * ha);
+        for b in 0..a {
+            let (pb, hb) = (free[b], steps[b]);
+            let value = (shift(&[(pa, ha), (pb, hb)])
+                - shift(&[(pa, ha), (pb, -hb)])
+                - shift(&[(pa, -ha), (pb, hb)])
+                + shift(&[(pa, -ha), (pb, -hb)]))
+                / (4.0 * ha * hb);
+            hessian[a][b] = value;
+            hessian[b][a] = value;
+        }
+    }
+    let inverse =
+        invert(hessian).ok_or_else(|| AnalysisError::FitFailed("Hessian is singular".into()))?;
+
+    let mut covariance = vec![vec![0.0; parameters.len()]; parameters.len()];
+    for a in 0..n {
+        for b in 0..n {
+            covariance[free[a]][free[b]] = 2.0 * inverse[a][b];
+        }
+    }
+    Ok(FitResult {
+        parameters: parameters
+            .iter()
+            .zip(&values)
+            .map(|(parameter, value)| Parameter {
+                value: *value,
+                ..parameter.clone()
+            })
+            .collect(),
+        errors: (0..parameters.len())
+            .map(|p| covariance[p][p].max(0.0).sqrt())
+            .collect(),
+        covariance,
+        objective: chi2,
+        ndf: points - free.len(),
+        converged,
+        iterations,
+    })
+}
+
+// Inverse of a square matrix with Gauss-Jordan elimination and partial pivoting
+pub(crate) fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .total_cmp(&matrix[*b][column].abs())
+        })?;
+        if matrix[pivot][column].abs() < 1e-300 || !matrix[pivot][column].is_finite() {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let scale = matrix[column][column];
+        for j in 0..n {
+            matrix[column][j] /= scale;
+            inverse[column][j] /= scale;
+        }
+        for row in 0..n {
+            if row != column {
+                let factor = matrix[row][column];
+                for j in 0..n {
+                    matrix[row][j] -= factor * matrix[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifetime::MUON_LIFETIME;
+    use crate::theory::TheoryFunction;
+
+    // Deterministic noise, uniform in [-1, 1)
+    fn noise(i: usize) -> f64 {
+        let x = (i as u64 + 1).wrapping_mul(6364136223846793005) >> 11;
+        x as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    #[test]
+    fn asymmetry_chi_square() {
+        // A(t) = 0.2 exp(-0.5 t) cos(2 pi 1.5 t + 10°), error 0.01
+        let time: Vec<f64> = (0..1000).map(|i| i as f64 * 0.01).collect();
+        let value = time
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                0.2 * (-0.5 * t).exp()
+                    * (2.0 * std::f64::consts::PI * 1.5 * t + 10f64.to_radians()).cos()
+                    + 0.01 * noise(i)
+            })
+            .collect();
+        let data = FitData {
+            error: vec![0.01; time.len()],
+            bins: vec![1; time.len()],
+            time,
+            value,
+        };
+        let theory = Theory::new(vec![vec![
+            TheoryFunction::Asymmetry(0),
+            TheoryFunction::SimpleExp(1),
+            TheoryFunction::TfCos(2, 3),
+        ]]);
+        let parameters = [
+            Parameter::new("Asy", 0.15),
+            Parameter::bounded("Rate", 1.0, 0.0, 10.0),
+            Parameter::new("Phase", 0.0),
+            Parameter::new("Frequency", 1.45),
+        ];
+        let run = FitRun::new(data, theory, FitType::Asymmetry);
+        let result = fit(
+            &parameters,
+            std::slice::from_ref(&run),
+            Estimator::ChiSquare,
+        )
+        .unwrap();
+        assert!(result.converged);
+        let values: Vec<f64> = result.parameters.iter().map(|p| p.value).collect();
+        for (value, expected) in values.iter().zip([0.2, 0.5, 10.0, 1.5]) {
+            assert!((value - expected).abs() < 0.02 * expected, "{:?}", values);
+        }
+        // Uniform noise of +-0.01 is an error of 0.01 / sqrt(3)
+        assert_eq!(result.ndf, 996);
+        assert!((result.objective / 1000.0 - 1.0 / 3.0).abs() < 0.05);
+        assert!(result.errors.iter().all(|error| *error > 0.0));
+        assert!(result.errors[0] < 0.005);
+
+        // Fixed parameters keep their value and have no error
+        let mut parameters = parameters;
+        parameters[3] = Parameter::fixed("Frequency", 1.5);
+        let result = fit(
+            &parameters,
+            std::slice::from_ref(&run),
+            Estimator::ChiSquare,
+        )
+        .unwrap();
+        assert_eq!(result.parameters[3].value, 1.5);
+        assert_eq!(result.errors[3], 0.0);
+        assert_eq!(result.ndf, 997);
+
+        // Bounds are respected
+        parameters[1] = Parameter::bounded("Rate", 1.0, 0.8, 10.0);
+        let result = fit(
+            &parameters,
+            std::slice::from_ref(&run),
+            Estimator::ChiSquare,
+        )
+        .unwrap();
+        assert_eq!(result.parameters[1].value, 0.8);
+
+        // A step far larger than the scale of the frequency gives a useless gradient, the fit gets stuck
+        parameters[3] = Parameter {
+            step: 1e3,
+            ..Parameter::new("Frequency", 1.45)
+        };
+        let result = fit(&parameters, &[run], Estimator::ChiSquare).unwrap();
+        assert!(!result.converged);
+        assert!(result.objective > 1000.0);
+    }
+
+    #[test]
+    fn single_histogram_likelihood() {
+        // N0 = 50 counts per bin, asymmetry 0.25 relaxing with 0.3 / µs, background 2, 4 bins per point
+        let time: Vec<f64> = (0..500).map(|i| i as f64 * 0.02).collect();
+        let expected = |t: f64| {
+            4.0 * (50.0 * (-t / MUON_LIFETIME).exp() * (1.0 + 0.25 * (-0.3 * t).exp()) + 2.0)
+        };
+        let value: Vec<f64> = time
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (expected(*t) + expected(*t).sqrt() * noise(i)).round())
+            .collect();
+        let data = FitData {
+            error: value.iter().map(|n: &f64| n.max(1.0).sqrt()).collect(),
+            bins: vec![4; time.len()],
+            time,
+            value,
+        };
+        let theory = Theory::new(vec![vec![
+            TheoryFunction::Asymmetry(0),
+            TheoryFunction::SimpleExp(1),
+        ]]);
+        let fit_type = FitType::SingleHistogram {
+            n0: 2,
+            background: 3,
+            tau: MUON_LIFETIME,
+        };
+        let parameters = [
+            Parameter::new("Asy", 0.2),
+            Parameter::new("Rate", 0.5),
+            Parameter::new("N0", 40.0),
+            Parameter::bounded("Bkg", 1.0, 0.0, 100.0),
+        ];
+        let run = FitRun::new(data, theory, fit_type);
+        for estimator in [Estimator::ChiSquare, Estimator::PoissonLikelihood] {
+            let result = fit(&parameters, std::slice::from_ref(&run), estimator).unwrap();
+            assert!(result.converged);
+            let values: Vec<f64> = result.parameters.iter().map(|p| p.value).collect();
+            for (i, expected) in [0.25, 0.3, 50.0, 2.0].iter().enumerate() {
+                assert!(
+                    (values[i] - expected).abs() < 3.0 * result.errors[i],
+                    "{:?} {:?}",
+                    values,
+                    result.errors
+                );
+            }
+        }
+
+        // The likelihood needs counts
+        let asymmetry = FitRun::new(run.data, run.theory, FitType::Asymmetry);
+        assert!(fit(&parameters, &[asymmetry], Estimator::PoissonLikelihood).is_err());
+    }
+
+    #[test]
+    fn shared_parameters() {
+        // Two runs with the same rate and different asymmetries
+        let time: Vec<f64> = (0..200).map(|i| i as f64 * 0.05).collect();
+        let run = |asymmetry: usize, value: f64| {
+            let data = FitData {
+                value: time.iter().map(|t| value * (-0.4 * t).exp()).collect(),
+                error: vec![0.01; time.len()],
+                bins: vec![1; time.len()],
+                time: time.clone(),
+            };
+            let theory = Theory::new(vec![vec![
+                TheoryFunction::Asymmetry(asymmetry),
+                TheoryFunction::SimpleExp(2),
+            ]]);
+            FitRun::new(data, theory, FitType::Asymmetry)
+        };
+        let parameters = [
+            Parameter::new("Asy1", 0.1),
+            Parameter::new("Asy2", 0.1),
+            Parameter::new("Rate", 1.0),
+        ];
+        let result = fit(
+            &parameters,
+            &[run(0, 0.2), run(1, 0.1)],
+            Estimator::ChiSquare,
+        )
+        .unwrap();
+        assert_eq!(result.ndf, 397);
+        assert!(result.objective < 1e-12);
+        assert!((result.parameters[0].value - 0.2).abs() < 1e-6);
+        assert!((result.parameters[1].value - 0.1).abs() < 1e-6);
+        assert!((result.parameters[2].value - 0.4).abs() < 1e-6);
+
+        assert_eq!(
+            fit(&parameters[..2], &[run(0, 0.2)], Estimator::ChiSquare),
+            Err(AnalysisError::FitFailed(
+                "Parameter 3 is not defined".into()
+            ))
+        );
+        // Free parameters have to be used, fixed ones may be left over
+        assert_eq!(
+            fit(&parameters, &[run(0, 0.2)], Estimator::ChiSquare),
+            Err(AnalysisError::FitFailed(
+                "Free parameter 2 is not used".into()
+            ))
+        );
+        let mut parameters = parameters;
+        parameters[1].fixed = true;
+        assert!(fit(&parameters, &[run(0, 0.2)], Estimator::ChiSquare).is_ok());
+    }
+
+    #[test]
+    fn raw_asymmetry_alpha() {
+        // Forward counters 20 % more efficient than backward counters
+        let time: Vec<f64> = (0..300).map(|i| i as f64 * 0.02).collect();
+        let alpha = 1.2;
+        let value = time
+            .iter()
+            .map(|t| {
+                let a = 0.25 * (-0.3 * t).exp();
+                let (f, b) = (alpha * (1.0 + a), 1.0 - a);
+                (f - b) / (f + b)
+            })
+            .collect();
+        let data = FitData {
+            error: vec![0.01; time.len()],
+            bins: vec![1; time.len()],
+            time,
+            value,
+        };
+        let theory = Theory::new(vec![vec![
+            TheoryFunction::Asymmetry(0),
+            TheoryFunction::SimpleExp(1),
+        ]]);
+        let parameters = [
+            Parameter::new("Asy", 0.2),
+            Parameter::new("Rate", 0.5),
+            Parameter::new("Alpha", 1.0),
+        ];
+        let run = FitRun::new(data, theory, FitType::RawAsymmetry { alpha: 2 });
+        let result = fit(&parameters, &[run], Estimator::ChiSquare).unwrap();
+        for (parameter, expected) in result.parameters.iter().zip([0.25, 0.3, 1.2]) {
+            assert!((parameter.value - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn matrix_inverse() {
+        let inverse = invert(vec![vec![4.0, 7.0], vec![2.0, 6.0]]).unwrap();
+        let expected = [[0.6, -0.7], [-0.2, 0.4]];
+        for (row, expected) in inverse.iter().zip(expected) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-12);
+            }
+        }
+        assert_eq!(invert(vec![vec![1.0, 2.0], vec![2.0, 4.0]]), None);
+    }
+}
@@ -0,0 +1,372 @@
+use std::f64::consts::PI;
+
+use serde::Serialize;
+
+use crate::asymmetry::Asymmetry;
+use crate::error::AnalysisError;
+use crate::lifetime::LifetimeCorrected;
+
+pub const MUON_GYROMAGNETIC_RATIO: f64 = 135.538817; // gamma / 2 pi in MHz/T
+const MAX_ZERO_PADDING: u32 = 24; // 2^24 points of the transform
+
+// Norton-Beer apodization, which suppresses the ringing from the end of the data at the cost of resolution.
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/user-manual.html#fourier-block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Apodization {
+    None,
+    Weak,
+    Medium,
+    Strong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FourierUnit {
+    MHz,
+    Gauss,
+    Tesla,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fourier {
+    pub apodization: Apodization,
+    pub zero_padding: Option<u32>, // transform 2^n points, n <= 24, at least the next power of two of the data length
+    pub unit: FourierUnit,
+    pub phase: Option<f64>, // in degrees for the phase corrected spectrum, None to choose it automatically
+    pub range: Option<(f64, f64)>, // time window in µs
+}
+
+// Spectrum from frequency 0 up to the Nyquist frequency. Amplitudes are normalized to the number of data
+// points and the phase refers to t = 0, not to the first data point.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FourierSpectrum {
+    pub unit: FourierUnit,
+    pub axis: Vec<f64>, // frequency or field in `unit`
+    pub real: Vec<f64>,
+    pub imaginary: Vec<f64>,
+    pub power: Vec<f64>,
+    pub phase_corrected: Vec<f64>, // real part after rotating by `phase`
+    pub phase: f64,                // in degrees
+}
+
+impl Default for Fourier {
+    fn default() -> Fourier {
+        Fourier {
+            apodization: Apodization::None,
+            zero_padding: None,
+            unit: FourierUnit::MHz,
+            phase: None,
+            range: None,
+        }
+    }
+}
+
+impl Apodization {
+    // Coefficients c_j of w(x) = sum c_j (1 - x^2)^j
+    fn coefficients(&self) -> &'static [f64] {
+        match self {
+            Apodization::None => &[1.0],
+            Apodization::Weak => &[0.384093, -0.087577, 0.703484],
+            Apodization::Medium => &[0.152442, -0.136176, 0.983734],
+            Apodization::Strong => &[0.045335, 0.0, 0.554883, 0.0, 0.399782],
+        }
+    }
+
+    // Window at `x`, the time relative to the length of the data
+    pub fn window(&self, x: f64) -> f64 {
+        let y = 1.0 - x * x;
+        self.coefficients()
+            .iter()
+            .rev()
+            .fold(0.0, |sum, c| sum * y + c)
+    }
+}
+
+impl FourierUnit {
+    // Value of `frequency` in MHz in this unit
+    pub fn from_mhz(&self, frequency: f64) -> f64 {
+        match self {
+            FourierUnit::MHz => frequency,
+            FourierUnit::Gauss => frequency / MUON_GYROMAGNETIC_RATIO * 1e4,
+            FourierUnit::Tesla => frequency / MUON_GYROMAGNETIC_RATIO,
+        }
+    }
+}
+
+impl Asymmetry {
+    pub fn fourier(&self, fourier: &Fourier) -> Result<FourierSpectrum, AnalysisError> {
+        fourier_transform(&self.time, &self.asymmetry, fourier)
+    }
+}
+
+impl LifetimeCorrected {
+    // Spectrum of a single histogram
+    pub fn fourier(&self, fourier: &Fourier) -> Result<FourierSpectrum, AnalysisError> {
+        fourier_transform(&self.time, &self.value, fourier)
+    }
+}
+
+// Spectrum of `value` at equidistant `time` in µs
+pub fn fourier_transform(
+    time: &[f64],
+    value: &[f64],
+    fourier: &Fourier,
+) -> Result<FourierSpectrum, AnalysisError> {
+    let (start, end) = fourier.range.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+    let (time, value): (Vec<f64>, Vec<f64>) = time
+        .iter()
+        .zip(value)
+        .filter(|(t, _)| **t >= start && **t <= end)
+        .unzip();
+    if time.len() < 2 {
+        return Err(AnalysisError::InvalidSpectrum(format!(
+            "{} points in the time window",
+            time.len()
+        )));
+    }
+    let step = (time[time.len() - 1] - time[0]) / (time.len() - 1) as f64;
+    if step <= 0.0
+        || time
+            .windows(2)
+            .any(|pair| ((pair[1] - pair[0]) - step).abs() > 1e-6 * step)
+    {
+        return Err(AnalysisError::InvalidSpectrum(
+            "Time bins are not equidistant".into(),
+        ));
+    }
+
+    let n = time.len();
+    let points = match fourier.zero_padding {
+        Some(power) if power > MAX_ZERO_PADDING => {
+            return Err(AnalysisError::InvalidSpectrum(format!(
+                "Zero padding 2^{} exceeds 2^{}",
+                power, MAX_ZERO_PADDING
+            )))
+        }
+        Some(power) => (1usize << power).max(n.next_power_of_two()),
+        None => n.next_power_of_two(),
+    };
+    let mut real = vec![0.0; points];
+    let mut imaginary = vec![0.0; points];
+    for (i, value) in value.iter().enumerate() {
+        real[i] = value * fourier.apodization.window(i as f64 / n as f64) / n as f64;
+    }
+    fft(&mut real, &mut imaginary);
+
+    // Frequencies up to the Nyquist frequency, shifted so that the phase refers to t = 0
+    let frequencies: Vec<f64> = (0..=points / 2)
+        .map(|j| j as f64 / (points as f64 * step))
+        .collect();
+    let (real, imaginary): (Vec<f64>, Vec<f64>) = frequencies
+        .iter()
+        .enumerate()
+        .map(|(j, frequency)| {
+            let angle = -2.0 * PI * frequency * time[0];
+            let (sin, cos) = angle.sin_cos();
+            (
+                real[j] * cos - imaginary[j] * sin,
+                real[j] * sin + imaginary[j] * cos,
+            )
+        })
+        .unzip();
+
+    // Without a given phase the phase of the strongest line above frequency 0, which is more precise with
+    // zero padding
+    let phase = fourier.phase.unwrap_or_else(|| {
+        let strongest = (1..real.len())
+            .max_by(|a, b| {
+                real[*a]
+                    .hypot(imaginary[*a])
+                    .total_cmp(&real[*b].hypot(imaginary[*b]))
+            })
+            .unwrap_or_default();
+        imaginary[strongest].atan2(real[strongest]).to_degrees()
+    });
+    let (sin, cos) = phase.to_radians().sin_cos();
+    Ok(FourierSpectrum {
+        unit: fourier.unit,
+        axis: frequencies
+            .iter()
+            .map(|frequency| fourier.unit.from_mhz(*frequency))
+            .collect(),
+        power: real
+            .iter()
+            .zip(&imaginary)
+            .map(|(re, im)| re * re + im * im)
+            .collect(),
+        phase_corrected: real
+            .iter()
+            .zip(&imaginary)
+            .map(|(re, im)| re * cos + im * sin)
+            .collect(),
+        real,
+        imaginary,
+        phase,
+    })
+}
+
+// In-place radix-2 fast Fourier transform X_j = sum x_k exp(-2 pi i j k / n), n a power of two
+fn fft(real: &mut [f64], imaginary: &mut [f64]) {
+    let n = real.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f64;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+                let re = real[b] * cos - imaginary[b] * sin;
+                let im = real[b] * sin + imaginary[b] * cos;
+                real[b] = real[a] - re;
+                imaginary[b] = imaginary[a] - im;
+                real[a] += re;
+                imaginary[a] += im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precession(frequency: f64, phase: f64, rate: f64) -> Asymmetry {
+        let time: Vec<f64> = (0..1000).map(|i| 0.1 + i as f64 * 0.01).collect();
+        Asymmetry {
+            asymmetry: time
+                .iter()
+                .map(|t| {
+                    0.2 * (-rate * t).exp() * (2.0 * PI * frequency * t + phase.to_radians()).cos()
+                })
+                .collect(),
+            error: vec![0.01; time.len()],
+            time,
+        }
+    }
+
+    fn maximum(values: &[f64]) -> usize {
+        (0..values.len())
+            .max_by(|a, b| values[*a].total_cmp(&values[*b]))
+            .unwrap()
+    }
+
+    #[test]
+    fn fft_matches_dft() {
+        let x: Vec<f64> = (0..16).map(|i| ((i * 7) % 5) as f64 - 2.0).collect();
+        let (mut real, mut imaginary) = (x.clone(), vec![0.0; 16]);
+        fft(&mut real, &mut imaginary);
+        for j in 0..16 {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (k, x) in x.iter().enumerate() {
+                let angle = -2.0 * PI * (j * k) as f64 / 16.0;
+                re += x * angle.cos();
+                im += x * angle.sin();
+            }
+            assert!((real[j] - re).abs() < 1e-12 && (imaginary[j] - im).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn precession_spectrum() {
+        let asymmetry = precession(1.5, 30.0, 0.2);
+        let spectrum = asymmetry.fourier(&Fourier::default()).unwrap();
+        assert_eq!(spectrum.axis.len(), 513);
+        // 1024 points of 10 ns give a resolution of 0.098 MHz
+        let peak = maximum(&spectrum.power);
+        assert!((spectrum.axis[peak] - 1.5).abs() < 0.05);
+        // The phase of the signal is found, the corrected spectrum is positive at the peak
+        assert!(spectrum.phase_corrected[peak] > 0.0);
+
+        // Field axis
+        let fourier = Fourier {
+            unit: FourierUnit::Gauss,
+            zero_padding: Some(14),
+            ..Default::default()
+        };
+        let spectrum = asymmetry.fourier(&fourier).unwrap();
+        assert_eq!(spectrum.axis.len(), 8193);
+        let peak = maximum(&spectrum.power);
+        assert!((spectrum.axis[peak] - 110.67).abs() < 0.5);
+        // With a fine frequency grid the phase of the signal is found and the phase corrected spectrum
+        // peaks at the line
+        assert!((spectrum.phase - 30.0).abs() < 2.0);
+        assert_eq!(maximum(&spectrum.phase_corrected), peak);
+        assert!((FourierUnit::Tesla.from_mhz(135.538817) - 1.0).abs() < 1e-12);
+
+        // A given phase is used as it is
+        let fourier = Fourier {
+            phase: Some(0.0),
+            ..Default::default()
+        };
+        let spectrum = asymmetry.fourier(&fourier).unwrap();
+        assert_eq!(spectrum.phase_corrected, spectrum.real);
+    }
+
+    #[test]
+    fn apodization() {
+        for apodization in [
+            Apodization::None,
+            Apodization::Weak,
+            Apodization::Medium,
+            Apodization::Strong,
+        ] {
+            assert!((apodization.window(0.0) - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(Apodization::Strong.window(1.0), 0.045335);
+
+        // Apodization broadens the line at the cost of its height
+        let asymmetry = precession(1.5, 0.0, 0.2);
+        let heights: Vec<f64> = [
+            Apodization::None,
+            Apodization::Weak,
+            Apodization::Medium,
+            Apodization::Strong,
+        ]
+        .into_iter()
+        .map(|apodization| {
+            let fourier = Fourier {
+                apodization,
+                zero_padding: Some(12),
+                ..Default::default()
+            };
+            let spectrum = asymmetry.fourier(&fourier).unwrap();
+            spectrum.power[maximum(&spectrum.power)]
+        })
+        .collect();
+        assert!(heights.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn invalid_data() {
+        let mut asymmetry = precession(1.0, 0.0, 0.2);
+        asymmetry.time[10] += 0.005;
+        assert!(asymmetry.fourier(&Fourier::default()).is_err());
+        let fourier = Fourier {
+            range: Some((100.0, 200.0)),
+            ..Default::default()
+        };
+        assert!(precession(1.0, 0.0, 0.2).fourier(&fourier).is_err());
+        let fourier = Fourier {
+            zero_padding: Some(64),
+            ..Default::default()
+        };
+        assert!(matches!(
+            precession(1.0, 0.0, 0.2).fourier(&fourier),
+            Err(AnalysisError::InvalidSpectrum(_))
+        ));
+    }
+}
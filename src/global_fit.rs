@@ -0,0 +1,377 @@
+use serde::Serialize;
+
+use crate::error::AnalysisError;
+use crate::fit::{fit, Estimator, FitData, FitResult, FitRun, FitType, Parameter};
+use crate::grouping::DetectorGroup;
+use crate::models::MusrRootFile;
+use crate::packing::Packing;
+use crate::physical_quantity::PhysicalQuantity;
+use crate::theory::Theory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Global, // one value shared by all runs, e.g. alpha
+    Run,    // an independent value for every run, e.g. a relaxation rate in a temperature scan
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalParameter {
+    pub parameter: Parameter, // start value, bounds and step, the same for every run of a per-run parameter
+    pub scope: Scope,
+}
+
+// Data taken from every run
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunData {
+    // Use alpha 1 together with `FitType::RawAsymmetry` to fit alpha
+    Asymmetry {
+        forward: DetectorGroup,
+        backward: DetectorGroup,
+        alpha: f64,
+        packing: Packing,
+    },
+    SingleHistogram {
+        group: DetectorGroup,
+        packing: Packing,
+    },
+}
+
+// The same fit of a set of runs, e.g. of a temperature scan. The theory and the fit type refer to the
+// parameters of `parameters`, which are expanded to one parameter per run for per-run parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalFit {
+    pub parameters: Vec<GlobalParameter>,
+    pub theory: Theory,
+    pub fit_type: FitType,
+    pub data: RunData,
+    pub range: (f64, f64), // fit range in µs
+    pub estimator: Estimator,
+}
+
+// Parameter values of one run, global parameters included
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunResult {
+    pub run_number: i64,
+    pub quantity: Option<PhysicalQuantity>, // header quantity the run is reported against
+    pub values: Vec<f64>,
+    pub errors: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanPoint {
+    pub quantity: f64,
+    pub quantity_error: Option<f64>,
+    pub value: f64,
+    pub error: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GlobalFitResult {
+    pub quantity: String, // label of the header quantity, e.g. Sample Temperature
+    pub unit: String,     // its unit, e.g. K
+    pub fit: FitResult,   // of the expanded parameters
+    pub runs: Vec<RunResult>,
+}
+
+impl GlobalParameter {
+    pub fn global(parameter: Parameter) -> GlobalParameter {
+        GlobalParameter {
+            parameter,
+            scope: Scope::Global,
+        }
+    }
+
+    pub fn run(parameter: Parameter) -> GlobalParameter {
+        GlobalParameter {
+            parameter,
+            scope: Scope::Run,
+        }
+    }
+}
+
+impl RunData {
+    pub fn extract(&self, musr_root_file: &MusrRootFile) -> Result<FitData, AnalysisError> {
+        match self {
+            RunData::Asymmetry {
+                forward,
+                backward,
+                alpha,
+                packing,
+            } => Ok(FitData::from(
+                &musr_root_file.packed_asymmetry(forward, backward, *alpha, None, packing)?,
+            )),
+            RunData::SingleHistogram { group, packing } => {
+                let grouped = musr_root_file.group(group)?;
+                Ok(FitData::from(&grouped.pack(packing, group.background)))
+            }
+        }
+    }
+}
+
+impl GlobalFit {
+    // Parameter indices of every run in the expanded parameter list. Global parameters come first in their
+    // order, followed by the per-run parameters of the first run, the second run, ...
+    pub fn parameter_map(&self, runs: usize) -> Vec<Vec<usize>> {
+        let globals = self
+            .parameters
+            .iter()
+            .filter(|p| p.scope == Scope::Global)
+            .count();
+        let per_run = self.parameters.len() - globals;
+        (0..runs)
+            .map(|run| {
+                let (mut global, mut local) = (0, 0);
+                self.parameters
+                    .iter()
+                    .map(|p| match p.scope {
+                        Scope::Global => {
+                            global += 1;
+                            global - 1
+                        }
+                        Scope::Run => {
+                            local += 1;
+                            globals + run * per_run + local - 1
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Fit all runs at once and report the parameters of every run against the header quantity `quantity`
+    pub fn fit(
+        &self,
+        musr_root_files: &[MusrRootFile],
+        quantity: &str,
+    ) -> Result<GlobalFitResult, AnalysisError> {
+        // Checked before the parameters are mapped to the runs
+        if let Some(index) = self
+            .theory
+            .parameters()
+            .into_iter()
+            .chain(self.fit_type.parameters())
+            .find(|index| *index >= self.parameters.len())
+        {
+            return Err(AnalysisError::FitFailed(format!(
+                "Parameter {} is not defined",
+                index + 1
+            )));
+        }
+        // Runs without the quantity are left out of the scan, all others have to agree on its unit
+        let mut units = musr_root_files
+            .iter()
+            .filter_map(|musr_root_file| musr_root_file.run_header.quantity(quantity))
+            .map(|quantity| quantity.unit);
+        let unit = units.next().unwrap_or_default();
+        if let Some(other) = units.find(|other| *other != unit) {
+            return Err(AnalysisError::FitFailed(format!(
+                "{} is given in {} and in {}",
+                quantity, unit, other
+            )));
+        }
+        let map = self.parameter_map(musr_root_files.len());
+        let mut parameters: Vec<Parameter> = self
+            .parameters
+            .iter()
+            .filter(|p| p.scope == Scope::Global)
+            .map(|p| p.parameter.clone())
+            .collect();
+        // Named by the position of the run, since the same run may be fitted twice or run numbers of
+        // different instruments may coincide
+        for run in 1..=musr_root_files.len() {
+            parameters.extend(
+                self.parameters
+                    .iter()
+                    .filter(|p| p.scope == Scope::Run)
+                    .map(|p| Parameter {
+                        name: format!("{}_{}", p.parameter.name, run),
+                        ..p.parameter.clone()
+                    }),
+            );
+        }
+
+        let runs = musr_root_files
+            .iter()
+            .zip(&map)
+            .map(|(musr_root_file, map)| {
+                let data = self.data.extract(musr_root_file)?;
+                Ok(FitRun::new(
+                    data.restrict(self.range.0, self.range.1),
+                    self.theory.remap(map),
+                    self.fit_type.remap(map),
+                ))
+            })
+            .collect::<Result<Vec<FitRun>, AnalysisError>>()?;
+        let result = fit(&parameters, &runs, self.estimator)?;
+
+        let runs = musr_root_files
+            .iter()
+            .zip(&map)
+            .map(|(musr_root_file, map)| RunResult {
+                run_number: musr_root_file.run_header.run_info.run_number,
+                quantity: musr_root_file.run_header.quantity(quantity),
+                values: map.iter().map(|p| result.parameters[*p].value).collect(),
+                errors: map.iter().map(|p| result.errors[*p]).collect(),
+            })
+            .collect();
+        Ok(GlobalFitResult {
+            quantity: quantity.to_string(),
+            unit,
+            fit: result,
+            runs,
+        })
+    }
+}
+
+impl GlobalFitResult {
+    // Value of parameter `parameter` (index into the parameters of the global fit) against the header
+    // quantity, sorted by the quantity. Runs without the quantity are left out, an unknown parameter gives no
+    // points.
+    pub fn scan(&self, parameter: usize) -> Vec<ScanPoint> {
+        let mut points: Vec<ScanPoint> = self
+            .runs
+            .iter()
+            .filter_map(|run| {
+                let quantity = run.quantity.as_ref()?;
+                Some(ScanPoint {
+                    quantity: quantity.value,
+                    quantity_error: quantity.error,
+                    value: *run.values.get(parameter)?,
+                    error: *run.errors.get(parameter)?,
+                })
+            })
+            .collect();
+        points.sort_by(|a, b| a.quantity.total_cmp(&b.quantity));
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifetime::MUON_LIFETIME;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+    use crate::theory::TheoryFunction;
+
+    fn scan_fit() -> GlobalFit {
+        // Asymmetry and background shared, N0 and relaxation rate per run
+        GlobalFit {
+            parameters: vec![
+                GlobalParameter::global(Parameter::new("Asy", 0.15)),
+                GlobalParameter::run(Parameter::bounded("Rate", 0.5, 0.0, 10.0)),
+                GlobalParameter::run(Parameter::new("N0", 90.0)),
+                GlobalParameter::global(Parameter::new("Bkg", 2.0)),
+            ],
+            theory: Theory::new(vec![vec![
+                TheoryFunction::Asymmetry(0),
+                TheoryFunction::SimpleExp(1),
+            ]]),
+            fit_type: FitType::SingleHistogram {
+                n0: 2,
+                background: 3,
+                tau: MUON_LIFETIME,
+            },
+            data: RunData::SingleHistogram {
+                group: DetectorGroup::new("forward", &[1]),
+                packing: Packing::Fixed(100),
+            },
+            range: (0.0, 10.0),
+            estimator: Estimator::ChiSquare,
+        }
+    }
+
+    #[test]
+    fn parameter_map() {
+        assert_eq!(
+            scan_fit().parameter_map(2),
+            [vec![0, 2, 3, 1], vec![0, 4, 5, 1]]
+        );
+    }
+
+    #[tokio::test]
+    async fn temperature_scan() {
+        // Three runs with rates 0.1, 0.2 and 0.3 / µs at 5, 10 and 15 K
+        let mut runs = vec![];
+        for run in 0..3 {
+            let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+                .await
+                .expect("Failed to parse file");
+            let rate = 0.1 * (run + 1) as f64;
+            let h_decay = &mut musr_root_file.histos.decay_ana_module.h_decay[0];
+            let bin_width = h_decay.bin_width / 1000.0;
+            for (bin, count) in h_decay.contents.iter_mut().enumerate() {
+                let t = (bin as f64 - 2834.0) * bin_width;
+                *count = 100.0 * (-t / MUON_LIFETIME).exp() * (1.0 + 0.2 * (-rate * t).exp()) + 1.0;
+            }
+            musr_root_file.run_header.run_info.sample_temperature =
+                PhysicalQuantity::parse(&format!("{} +- 0.01 K", 15 - 5 * run));
+            runs.push(musr_root_file);
+        }
+
+        let result = scan_fit().fit(&runs, "Sample Temperature").unwrap();
+        assert!(result.fit.converged);
+        assert_eq!(result.unit, "K");
+        assert_eq!(result.fit.parameters.len(), 8);
+        // All runs are run 2000
+        let names: Vec<&str> = result
+            .fit
+            .parameters
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["Asy", "Bkg", "Rate_1", "N0_1", "Rate_2", "N0_2", "Rate_3", "N0_3"]
+        );
+        assert_eq!(result.runs.len(), 3);
+        // Packed bins are compared with the model at their center, which leaves a small bias
+        for run in &result.runs {
+            assert!((run.values[0] - 0.2).abs() < 1e-4);
+            assert!((run.values[2] - 100.0).abs() < 1e-2);
+            assert!((run.values[3] - 1.0).abs() < 1e-3);
+        }
+
+        // Sorted by temperature, the rate decreases
+        let scan = result.scan(1);
+        let temperatures: Vec<f64> = scan.iter().map(|point| point.quantity).collect();
+        assert_eq!(temperatures, [5.0, 10.0, 15.0]);
+        for (point, rate) in scan.iter().zip([0.3, 0.2, 0.1]) {
+            assert!((point.value - rate).abs() < 1e-4);
+            assert!(point.error > 0.0);
+            assert_eq!(point.quantity_error, Some(0.01));
+        }
+        assert!(result.scan(4).is_empty());
+
+        // Undefined parameters are reported
+        let mut undefined = scan_fit();
+        undefined.theory = Theory::new(vec![vec![TheoryFunction::SimpleExp(4)]]);
+        assert_eq!(
+            undefined.fit(&runs, "Sample Temperature"),
+            Err(AnalysisError::FitFailed(
+                "Parameter 5 is not defined".into()
+            ))
+        );
+
+        // The quantity has to be given in the same unit by all runs
+        runs[2].run_header.run_info.sample_temperature = PhysicalQuantity::parse("5000 +- 10 mK");
+        assert_eq!(
+            scan_fit().fit(&runs, "Sample Temperature"),
+            Err(AnalysisError::FitFailed(
+                "Sample Temperature is given in K and in mK".into()
+            ))
+        );
+        runs[2].run_header.run_info.sample_temperature = PhysicalQuantity::parse("5 +- 0.01 K");
+
+        // Missing histograms are reported
+        let mut missing = scan_fit();
+        missing.data = RunData::SingleHistogram {
+            group: DetectorGroup::new("none", &[999]),
+            packing: Packing::Fixed(100),
+        };
+        assert_eq!(
+            missing.fit(&runs, "Sample Temperature"),
+            Err(AnalysisError::MissingHistogram(999))
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::background::{estimate, Background, BackgroundEstimate};
+use crate::error::AnalysisError;
+use crate::models::MusrRootFile;
+use crate::packing::{pack, PackedHistogram, Packing};
+
+// A named set of decay histograms analysed as one, e.g. all forward counters
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorGroup {
+    pub name: String,                   // e.g. forward
+    pub histograms: Vec<i64>, // hDecay numbers, i.e. Histo Number + red / green offset, e.g. [1, 2] or [41, 42]
+    pub background: BackgroundEstimate, // counts per bin subtracted from the sum of the group
+}
+
+// Sum of the histograms of a group. Every histogram is shifted by whole bins so that its t0 falls onto
+// the t0 of the first histogram before summing. The remaining fractional differences are averaged into
+// the effective t0; the good-bin window is the one common to all histograms.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupedHistogram {
+    pub name: String,
+    pub histograms: Vec<i64>,
+    pub contents: Vec<f64>,
+    pub time_zero_bin: f64, // effective t0, may be fractional
+    pub first_good_bin: i64,
+    pub last_good_bin: i64,
+    pub bin_width: f64, // in ns
+}
+
+impl DetectorGroup {
+    pub fn new(name: &str, histograms: &[i64]) -> DetectorGroup {
+        DetectorGroup {
+            name: name.to_string(),
+            histograms: histograms.to_vec(),
+            background: BackgroundEstimate::default(),
+        }
+    }
+}
+
+impl MusrRootFile {
+    pub fn group(&self, group: &DetectorGroup) -> Result<GroupedHistogram, AnalysisError> {
+        let detector_info = &self.run_header.detector_info;
+        let mut grouped: Option<GroupedHistogram> = None;
+        let mut t0_sum = 0.0;
+        for number in &group.histograms {
+            let h_decay = self
+                .histos
+                .decay_ana_module
+                .h_decay
+                .iter()
+                .find(|h_decay| h_decay.number == *number)
+                .ok_or(AnalysisError::MissingHistogram(*number))?;
+            let detector = h_decay
+                .detector(detector_info)
+                .ok_or(AnalysisError::MissingDetector(*number))?;
+            let t0 = detector.t0_bin()?;
+            let grouped = grouped.get_or_insert_with(|| GroupedHistogram {
+                name: group.name.clone(),
+                histograms: vec![],
+                contents: vec![0.0; h_decay.contents.len()],
+                time_zero_bin: t0,
+                first_good_bin: i64::MIN,
+                last_good_bin: i64::MAX,
+                bin_width: h_decay.bin_width,
+            });
+
+            // Bin i of the sum corresponds to bin i + shift of this histogram
+            let shift = (t0 - grouped.time_zero_bin).round() as i64;
+            for (bin, count) in grouped.contents.iter_mut().enumerate() {
+                let source = bin as i64 + shift;
+                *count += usize::try_from(source)
+                    .ok()
+                    .and_then(|source| h_decay.contents.get(source))
+                    .unwrap_or(&0.0);
+            }
+            grouped.histograms.push(*number);
+            grouped.first_good_bin = grouped.first_good_bin.max(detector.first_good_bin - shift);
+            grouped.last_good_bin = grouped.last_good_bin.min(detector.last_good_bin - shift);
+            t0_sum += t0 - shift as f64;
+        }
+
+        let mut grouped = grouped.ok_or(AnalysisError::EmptyGroup)?;
+        grouped.time_zero_bin = t0_sum / grouped.histograms.len() as f64;
+        grouped.first_good_bin = grouped.first_good_bin.max(0);
+        grouped.last_good_bin = grouped.last_good_bin.min(grouped.contents.len() as i64 - 1);
+        if grouped.first_good_bin > grouped.last_good_bin {
+            return Err(AnalysisError::EmptyWindow);
+        }
+        Ok(grouped)
+    }
+}
+
+impl GroupedHistogram {
+    // Time of the center of `bin` relative to t0 in µs
+    pub fn time(&self, bin: usize) -> f64 {
+        (bin as f64 - self.time_zero_bin) * self.bin_width / 1000.0
+    }
+
+    pub fn time_axis(&self) -> Vec<f64> {
+        (0..self.contents.len()).map(|bin| self.time(bin)).collect()
+    }
+
+    pub fn good_bins(&self) -> Range<usize> {
+        self.first_good_bin as usize..self.last_good_bin as usize + 1
+    }
+
+    pub fn good_counts(&self) -> &[f64] {
+        &self.contents[self.good_bins()]
+    }
+
+    pub fn good_times(&self) -> Vec<f64> {
+        self.good_bins().map(|bin| self.time(bin)).collect()
+    }
+
+    pub fn background(&self, background: &Background) -> Result<BackgroundEstimate, AnalysisError> {
+        estimate(&self.contents, self.time_zero_bin, background)
+    }
+
+    pub fn pack(&self, packing: &Packing, background: BackgroundEstimate) -> PackedHistogram {
+        pack(&self.good_times(), self.good_counts(), packing, background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    #[tokio::test]
+    async fn lem_groups() {
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+
+        let forward = DetectorGroup::new("forward", &[1, 2, 3, 4]);
+        let grouped = musr_root_file.group(&forward).unwrap();
+        assert_eq!(grouped.name, "forward");
+        assert_eq!(grouped.time_zero_bin, 2834.0);
+        assert_eq!(
+            (grouped.first_good_bin, grouped.last_good_bin),
+            (2834, 66600)
+        );
+        let h_decay = &musr_root_file.histos.decay_ana_module.h_decay;
+        let sum: f64 = h_decay[..4]
+            .iter()
+            .map(|h| h.contents.iter().sum::<f64>())
+            .sum();
+        assert_eq!(grouped.contents.iter().sum::<f64>(), sum);
+        assert!(grouped.background(&Background::PreT0).unwrap().value > 0.0);
+        assert_eq!(grouped.good_times()[0], 0.0);
+
+        // Detector 2 with t0 shifted by 10.4 bins and a later first good bin
+        let detectors = &mut musr_root_file.run_header.detector_info.detectors;
+        detectors[1].time_zero_bin = Some(2844.4);
+        detectors[1].first_good_bin = 2850;
+        let grouped = musr_root_file.group(&forward).unwrap();
+        assert_eq!(grouped.time_zero_bin, 2834.1);
+        assert_eq!(grouped.first_good_bin, 2840);
+        let h_decay = &musr_root_file.histos.decay_ana_module.h_decay;
+        assert_eq!(
+            grouped.contents[3000],
+            h_decay[0].contents[3000]
+                + h_decay[1].contents[3010]
+                + h_decay[2].contents[3000]
+                + h_decay[3].contents[3000]
+        );
+
+        assert_eq!(
+            musr_root_file.group(&DetectorGroup::new("none", &[])),
+            Err(AnalysisError::EmptyGroup)
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use serde::Serialize;
+
+use crate::error::ParsingError;
+use crate::physical_quantity::PhysicalQuantity;
+
+// A single RunHeader entry of the form `<number> - <label>: <value> -@<type>`, e.g.
+//  009 - Run Duration: 17305 sec -@3
+// The type tags are defined by TMusrRunHeader:
+//  -@0 TString, -@1 Int_t, -@2 Double_t, -@3 TMusrRunPhysicalQuantity,
+//  -@4 TStringVector, -@5 TIntVector, -@6 TDoubleVector
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeaderEntry {
+    pub index: u32,    // running number of the entry, e.g. 9
+    pub label: String, // e.g. Run Duration
+    pub value: String, // raw value as written, e.g. 17305 sec
+    pub type_tag: u8,  // e.g. 3
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum HeaderValue {
+    String(String),                     // -@0
+    Int(i64),                           // -@1
+    Double(f64),                        // -@2
+    PhysicalQuantity(PhysicalQuantity), // -@3
+    StringVector(Vec<String>),          // -@4, elements separated by `;`
+    IntVector(Vec<i64>),                // -@5
+    DoubleVector(Vec<f64>),             // -@6
+}
+
+impl HeaderEntry {
+    pub fn parse(line: &str) -> Result<HeaderEntry, ParsingError> {
+        let error = |reason: &str| ParsingError::ParseError(format!("{}: '{}'", reason, line));
+
+        let (index, entry) = line
+            .split_once(" - ")
+            .ok_or_else(|| error("Missing entry number"))?;
+        let index = index
+            .trim()
+            .parse()
+            .map_err(|_| error("Invalid entry number"))?;
+        let (entry, type_tag) = entry
+            .rsplit_once("-@")
+            .ok_or_else(|| error("Missing type tag"))?;
+        let type_tag = type_tag
+            .trim()
+            .parse()
+            .ok()
+            .filter(|tag| *tag <= 6)
+            .ok_or_else(|| error("Invalid type tag"))?;
+        let (label, value) = entry
+            .split_once(':')
+            .ok_or_else(|| error("Missing label"))?;
+
+        Ok(HeaderEntry {
+            index,
+            label: label.trim().to_string(),
+            value: value.trim().to_string(),
+            type_tag,
+        })
+    }
+
+    pub fn typed_value(&self) -> Result<HeaderValue, ParsingError> {
+        let error = || {
+            ParsingError::ParseError(format!(
+                "Value '{}' of '{}' does not match type tag -@{}",
+                self.value, self.label, self.type_tag
+            ))
+        };
+
+        let value = self.value.as_str();
+        Ok(match self.type_tag {
+            0 => HeaderValue::String(value.to_string()),
+            1 => HeaderValue::Int(value.parse().map_err(|_| error())?),
+            2 => HeaderValue::Double(value.parse().map_err(|_| error())?),
+            3 => HeaderValue::PhysicalQuantity(PhysicalQuantity::parse(value).ok_or_else(error)?),
+            4 => HeaderValue::StringVector(elements(value).map(str::to_string).collect()),
+            5 => HeaderValue::IntVector(
+                elements(value)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error())?,
+            ),
+            6 => HeaderValue::DoubleVector(
+                elements(value)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error())?,
+            ),
+            _ => return Err(error()),
+        })
+    }
+}
+
+// Split the value of a vector entry, e.g. `0; 20; 40; 60`
+fn elements(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+}
+
+impl HeaderValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            HeaderValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            HeaderValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // Integers are accepted as well, since e.g. `Time Zero Bin` is sometimes written as -@1
+    pub fn as_double(&self) -> Option<f64> {
+        match self {
+            HeaderValue::Double(value) => Some(*value),
+            HeaderValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_physical_quantity(&self) -> Option<&PhysicalQuantity> {
+        match self {
+            HeaderValue::PhysicalQuantity(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_vector(&self) -> Option<&[i64]> {
+        match self {
+            HeaderValue::IntVector(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_vector(&self) -> Option<&[String]> {
+        match self {
+            HeaderValue::StringVector(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_double_vector(&self) -> Option<&[f64]> {
+        match self {
+            HeaderValue::DoubleVector(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entries() {
+        let entry = HeaderEntry::parse("000 - Version: git-sha: dae9ef0ffba4 -@0").unwrap();
+        assert_eq!(entry.index, 0);
+        assert_eq!(entry.label, "Version");
+        assert_eq!(entry.value, "git-sha: dae9ef0ffba4");
+        assert_eq!(
+            entry.typed_value().unwrap(),
+            HeaderValue::String("git-sha: dae9ef0ffba4".into())
+        );
+
+        let entry =
+            HeaderEntry::parse("016 - Muon Source: Target E - Low Energy Muons -@0").unwrap();
+        assert_eq!(entry.value, "Target E - Low Energy Muons");
+
+        let entry = HeaderEntry::parse("009 - Run Duration: 17305 sec -@3").unwrap();
+        assert_eq!(entry.index, 9);
+        let value = entry.typed_value().unwrap();
+        let duration = value.as_physical_quantity().unwrap();
+        assert_eq!(duration.value, 17305.0);
+        assert_eq!(duration.unit, "sec");
+
+        let entry = HeaderEntry::parse("028 - Time Zero Bin: 2834.000000 -@2").unwrap();
+        assert_eq!(entry.typed_value().unwrap(), HeaderValue::Double(2834.0));
+
+        let entry = HeaderEntry::parse("024 - RedGreen Offsets: 0; 20; 40; 60 -@5").unwrap();
+        assert_eq!(
+            entry.typed_value().unwrap(),
+            HeaderValue::IntVector(vec![0, 20, 40, 60])
+        );
+
+        let entry = HeaderEntry::parse("042 - Slits: 1.5; 2.25 -@6").unwrap();
+        assert_eq!(
+            entry.typed_value().unwrap(),
+            HeaderValue::DoubleVector(vec![1.5, 2.25])
+        );
+
+        let entry = HeaderEntry::parse("043 - Counters: a; b -@4").unwrap();
+        assert_eq!(
+            entry.typed_value().unwrap().as_string_vector().unwrap(),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn reject_malformed_entries() {
+        assert!(HeaderEntry::parse("0000 Tue Jul 23 12:13:13 2024 Run 2000 started.").is_err());
+        assert!(HeaderEntry::parse("008 - Run Number: 2000").is_err());
+        assert!(HeaderEntry::parse("008 - Run Number: 2000 -@9").is_err());
+
+        let entry = HeaderEntry::parse("008 - Run Number: two thousand -@1").unwrap();
+        assert!(entry.typed_value().is_err());
+
+        let entry = HeaderEntry::parse("011 - Run Duration: n/a -@3").unwrap();
+        assert!(entry.typed_value().is_err());
+    }
+}
@@ -0,0 +1,21 @@
+pub mod asymmetry;
+pub mod background;
+pub mod error;
+pub mod fit;
+pub mod fourier;
+pub mod global_fit;
+pub mod grouping;
+pub mod header_entry;
+pub mod lifetime;
+pub mod max_ent;
+pub mod models;
+pub mod msr;
+pub mod musr_root_file_parser;
+pub mod packing;
+pub mod physical_quantity;
+pub mod slow_control;
+pub mod t0;
+pub mod theory;
+pub mod time_axis;
+pub mod validation;
+pub mod xml_export;
@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+use crate::packing::PackedHistogram;
+
+pub const MUON_LIFETIME: f64 = 2.1969811; // in µs
+
+// Single histogram representation of a decay histogram
+//  N(t) = N0 exp(-t / tau) (1 + A(t)) + background
+// Removing the background and the muon decay and normalizing to N0 leaves A(t):
+//  A(t) = (N(t) - background) exp(t / tau) / N0 - 1
+// N0 is estimated from the data as the number of counts divided by the sum of exp(-t / tau) over the
+// bins, i.e. assuming that A(t) averages out over the good-bin window.
+//
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/user-manual.html#single-histogram-fit-type-0
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LifetimeCorrected {
+    pub time: Vec<f64>, // in µs relative to t0
+    pub value: Vec<f64>,
+    pub error: Vec<f64>,
+    pub n0: f64, // counts per (unpacked) bin at t0
+    pub n0_error: f64,
+}
+
+impl PackedHistogram {
+    // Lifetime corrected spectrum of a background corrected histogram, `tau` in µs
+    pub fn lifetime_corrected(&self, tau: f64) -> LifetimeCorrected {
+        let decay: Vec<f64> = self.time.iter().map(|t| (-t / tau).exp()).collect();
+        let expected: f64 = self
+            .bins
+            .iter()
+            .zip(&decay)
+            .map(|(bins, decay)| *bins as f64 * decay)
+            .sum();
+        let counts: f64 = self.counts.iter().sum();
+        let variance: f64 = self.errors.iter().map(|error| error.powi(2)).sum();
+        let (n0, n0_error) = if expected > 0.0 {
+            (counts / expected, variance.sqrt() / expected)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let mut corrected = LifetimeCorrected {
+            time: self.time.clone(),
+            value: vec![],
+            error: vec![],
+            n0,
+            n0_error,
+        };
+        for (i, decay) in decay.iter().enumerate() {
+            // Expected counts of the bin without asymmetry
+            let norm = n0 * self.bins[i] as f64 * decay;
+            if norm > 0.0 {
+                corrected.value.push(self.counts[i] / norm - 1.0);
+                corrected.error.push(self.errors[i] / norm);
+            } else {
+                corrected.value.push(0.0);
+                corrected.error.push(1.0);
+            }
+        }
+        corrected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::BackgroundEstimate;
+    use crate::packing::{pack, Packing};
+
+    #[test]
+    fn recovers_asymmetry() {
+        // N0 = 1000 counts per bin, A(t) = 0.2 cos(2 pi t), background 10
+        let times: Vec<f64> = (0..2000).map(|bin| bin as f64 * 0.005).collect();
+        let asymmetry = |t: f64| 0.2 * (2.0 * std::f64::consts::PI * t).cos();
+        let counts: Vec<f64> = times
+            .iter()
+            .map(|t| 1000.0 * (-t / MUON_LIFETIME).exp() * (1.0 + asymmetry(*t)) + 10.0)
+            .collect();
+        let background = BackgroundEstimate::fixed(10.0);
+
+        let packed = pack(&times, &counts, &Packing::Fixed(1), background);
+        let corrected = packed.lifetime_corrected(MUON_LIFETIME);
+        assert!((corrected.n0 - 1000.0).abs() < 2.0);
+        assert!(corrected.n0_error > 0.0);
+        for (t, value) in corrected.time.iter().zip(&corrected.value) {
+            assert!((value - asymmetry(*t)).abs() < 2e-3);
+        }
+        // Errors grow with exp(t / 2 tau)
+        let last = corrected.error.len() - 1;
+        assert!(corrected.error[last] > 5.0 * corrected.error[0]);
+
+        // N0 stays per bin when packing
+        let packed = pack(&times, &counts, &Packing::Fixed(5), background);
+        let corrected = packed.lifetime_corrected(MUON_LIFETIME);
+        assert!((corrected.n0 - 1000.0).abs() < 2.0);
+    }
+}
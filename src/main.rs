@@ -1,25 +1,306 @@
-mod error;
-mod models;
-mod musr_root_file_parser;
-
-use crate::error::ParsingError;
-use crate::musr_root_file_parser::parse_musr_root_file;
-
-fn main() -> Result<(), ParsingError> {
-    // Get the file path from command line arguments
-    // let args: Vec<String> = env::args().collect();
-    // if args.len() != 2 {
-    //     eprintln!("Usage: {} <path to binary file>", args[0]);
-    //     std::process::exit(1);
-    // }
-    // let file_path = &args[1];
-    // let mut file = File::open("./src/lem24_his_2000.root")?;
-
-    // Parse the file
-    let musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")?;
-
-    // Do something with the parsed data
-    println!("{:?}", musr_root_file);
-
-    Ok(())
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::json;
+
+use plotting_data::models::MusrRootFile;
+use plotting_data::musr_root_file_parser::parse_musr_root_file;
+use plotting_data::validation::Severity;
+
+// Exit codes
+const EXIT_INVALID: i32 = 1; // `validate` found errors
+const EXIT_PARSE_FAILURE: i32 = 2; // at least one file could not be read
+const EXIT_WRITE_FAILURE: i32 = 3; // output could not be written
+
+#[tokio::main]
+async fn main() {
+    let files = || {
+        Arg::with_name("FILES")
+            .help("MusrRoot files or glob patterns, e.g. 'data/lem24_his_*.root'")
+            .required(true)
+            .multiple(true)
+    };
+    let json = || Arg::with_name("json").long("json").help("Output as JSON");
+    let matches = App::new("Inspect MusrRoot files")
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .after_help(
+            "EXIT CODES:\n    0    success\n    1    validation found errors, or invalid arguments\n    2    a file could not be parsed\n    3    output could not be written",
+        )
+        .subcommand(
+            SubCommand::with_name("header")
+                .about("Print the run header")
+                .arg(json())
+                .arg(files()),
+        )
+        .subcommand(
+            SubCommand::with_name("histos")
+                .about("List the histograms of each histos sub-folder")
+                .arg(json())
+                .arg(files()),
+        )
+        .subcommand(
+            SubCommand::with_name("detectors")
+                .about("List the detectors of the run header")
+                .arg(json())
+                .arg(files()),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the file structure as XML as expected by MusrRoot.xsd")
+                .arg(
+                    Arg::with_name("output-dir")
+                        .long("output-dir")
+                        .short("o")
+                        .takes_value(true)
+                        .help(
+                            "Write <file name>.xml into this directory instead of printing, required for several files",
+                        ),
+                )
+                .arg(files()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check files against the rules of MusrRoot.xsd")
+                .arg(json())
+                .arg(files()),
+        )
+        .get_matches();
+
+    let (command, sub_matches) = matches.subcommand();
+    let sub_matches = sub_matches.expect("A subcommand is required");
+    let as_json = sub_matches.is_present("json");
+    let paths = expand_globs(sub_matches.values_of("FILES").unwrap_or_default());
+    if command == "export" && paths.len() > 1 && !sub_matches.is_present("output-dir") {
+        // Several XML documents in one stream would not be a valid document
+        clap::Error::with_description(
+            "Exporting several files requires --output-dir",
+            clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut exit_code = 0;
+    let mut json_output = Vec::new();
+    for path in &paths {
+        let musr_root_file = match parse_musr_root_file(path).await {
+            Ok(musr_root_file) => musr_root_file,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                if as_json {
+                    json_output.push(json!({ "file": path, "error": err.to_string() }));
+                }
+                exit_code = EXIT_PARSE_FAILURE;
+                continue;
+            }
+        };
+        if paths.len() > 1 && !as_json && command != "export" {
+            check_output(writeln!(out, "==> {} <==", path), exit_code);
+        }
+        let output = match command {
+            "header" => header(&musr_root_file, as_json, &mut out),
+            "histos" => histos(&musr_root_file, as_json, &mut out),
+            "detectors" => detectors(&musr_root_file, as_json, &mut out),
+            "export" => match export(&musr_root_file, path, sub_matches, &mut out) {
+                Ok(()) => Ok(None),
+                Err(ExportError::Output(err)) => Err(err),
+                Err(ExportError::File(file, err)) => {
+                    eprintln!("{}: could not write {}: {}", path, file.display(), err);
+                    exit_code = EXIT_WRITE_FAILURE;
+                    Ok(None)
+                }
+            },
+            "validate" => validate(&musr_root_file, as_json, &mut out).map(|(valid, output)| {
+                if !valid && exit_code == 0 {
+                    exit_code = EXIT_INVALID;
+                }
+                output
+            }),
+            _ => unreachable!("Unknown subcommand {}", command),
+        };
+        if let Some(value) = check_output(output, exit_code) {
+            json_output.push(json!({ "file": path, command: value }));
+        }
+    }
+    if as_json {
+        let json_output =
+            serde_json::to_string_pretty(&json_output).expect("Failed to serialize output");
+        check_output(writeln!(out, "{}", json_output), exit_code);
+    }
+    check_output(out.flush(), exit_code);
+    process::exit(exit_code);
+}
+
+enum ExportError {
+    Output(io::Error),        // printing to stdout failed
+    File(PathBuf, io::Error), // writing the XML file failed
+}
+
+// Stop if stdout cannot be written. A closed pipe, e.g. of `plotting_data header *.root | head`, is not an
+// error of this program and keeps the exit code reached so far.
+fn check_output<T>(result: io::Result<T>, exit_code: i32) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => process::exit(exit_code),
+        Err(err) => {
+            eprintln!("Could not write output: {}", err);
+            process::exit(EXIT_WRITE_FAILURE)
+        }
+    }
+}
+
+// Expand glob patterns; arguments which match nothing are passed on as they are so that they are reported
+fn expand_globs<'a>(args: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut paths = Vec::new();
+    for arg in args {
+        let matches: Vec<String> = glob::glob(arg)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|path| path.display().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if matches.is_empty() {
+            paths.push(arg.to_string());
+        } else {
+            paths.extend(matches);
+        }
+    }
+    paths
+}
+
+fn header(
+    musr_root_file: &MusrRootFile,
+    as_json: bool,
+    out: &mut impl Write,
+) -> io::Result<Option<serde_json::Value>> {
+    let run_header = &musr_root_file.run_header;
+    if as_json {
+        return Ok(Some(json!(run_header)));
+    }
+    // In the order of the file
+    let mut sections: Vec<_> = run_header.entries().iter().collect();
+    sections.sort_by_key(|(_, entries)| entries.first().map(|entry| entry.index));
+    for (section, entries) in sections {
+        writeln!(out, "{}", section)?;
+        for entry in entries {
+            writeln!(
+                out,
+                "  {:03} - {}: {}",
+                entry.index, entry.label, entry.value
+            )?;
+        }
+    }
+    if !run_header.run_summary.is_empty() {
+        writeln!(out, "RunSummary")?;
+        for line in &run_header.run_summary {
+            writeln!(out, "  {}", line)?;
+        }
+    }
+    Ok(None)
+}
+
+fn histos(
+    musr_root_file: &MusrRootFile,
+    as_json: bool,
+    out: &mut impl Write,
+) -> io::Result<Option<serde_json::Value>> {
+    let folders = &musr_root_file.histos.folders;
+    if as_json {
+        return Ok(Some(json!(folders)));
+    }
+    for (folder, histograms) in folders {
+        writeln!(out, "{} ({} histograms)", folder, histograms.len())?;
+        for histogram in histograms {
+            writeln!(out, "  {} [{}]", histogram.name, histogram.class_name)?;
+        }
+    }
+    Ok(None)
+}
+
+fn detectors(
+    musr_root_file: &MusrRootFile,
+    as_json: bool,
+    out: &mut impl Write,
+) -> io::Result<Option<serde_json::Value>> {
+    let detectors = &musr_root_file.run_header.detector_info.detectors;
+    if as_json {
+        return Ok(Some(json!(detectors)));
+    }
+    // The name column is as wide as the longest name
+    let width = detectors
+        .iter()
+        .map(|detector| detector.name.chars().count())
+        .chain(["Name".len()])
+        .max()
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "{:<width$} {:>5} {:>8} {:>12} {:>10} {:>10}",
+        "Name", "Histo", "Length", "t0 bin", "First good", "Last good"
+    )?;
+    for detector in detectors {
+        writeln!(
+            out,
+            "{:<width$} {:>5} {:>8} {:>12} {:>10} {:>10}",
+            detector.name,
+            detector.histo_number,
+            detector.histo_length,
+            detector
+                .time_zero_bin
+                .map_or("-".to_string(), |bin| bin.to_string()),
+            detector.first_good_bin,
+            detector.last_good_bin
+        )?;
+    }
+    Ok(None)
+}
+
+fn export(
+    musr_root_file: &MusrRootFile,
+    path: &str,
+    sub_matches: &ArgMatches,
+    out: &mut impl Write,
+) -> Result<(), ExportError> {
+    let xml = musr_root_file.to_xml();
+    match sub_matches.value_of("output-dir") {
+        Some(dir) => {
+            let stem = Path::new(path)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("musr_root");
+            let file = Path::new(dir).join(format!("{}.xml", stem));
+            fs::write(&file, xml).map_err(|err| ExportError::File(file, err))
+        }
+        None => write!(out, "{}", xml).map_err(ExportError::Output),
+    }
+}
+
+// Returns whether the file is free of errors
+fn validate(
+    musr_root_file: &MusrRootFile,
+    as_json: bool,
+    out: &mut impl Write,
+) -> io::Result<(bool, Option<serde_json::Value>)> {
+    let findings = musr_root_file.validate();
+    let valid = findings
+        .iter()
+        .all(|finding| finding.severity != Severity::Error);
+    if as_json {
+        return Ok((valid, Some(json!(findings))));
+    }
+    if findings.is_empty() {
+        writeln!(out, "valid")?;
+    }
+    for finding in &findings {
+        writeln!(out, "{}", finding)?;
+    }
+    Ok((valid, None))
 }
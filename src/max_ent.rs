@@ -0,0 +1,513 @@
+use std::f64::consts::PI;
+
+use serde::Serialize;
+
+use crate::background::{estimate, Background};
+use crate::error::AnalysisError;
+use crate::fit::invert;
+use crate::fourier::MUON_GYROMAGNETIC_RATIO;
+use crate::header_entry::HeaderValue;
+use crate::lifetime::MUON_LIFETIME;
+use crate::models::{Detector, MusrRootFile};
+use crate::packing::{pack, Packing};
+
+const MAX_ITERATIONS: usize = 200;
+const MAX_DEAD_TIME_LOSS: f64 = 0.5; // fraction of the counts of a bin
+
+// Settings of one detector. Without a phase or dead time the instrument specific detector entries `Phase`
+// (in degrees) and `Dead Time` (in ns) are used, or 0 if they are missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxEntDetector {
+    pub histogram: i64, // hDecay number
+    pub phase: Option<f64>,
+    pub dead_time: Option<f64>,
+}
+
+// Maximum entropy reconstruction of the field distribution p(B) from the lifetime corrected spectra of
+// several detectors. The spectrum of detector d is modelled as
+//  A_d(t) = sum_k p_k cos(gamma B_k t + phi_d)
+// and p is found by maximizing alpha S - chi^2 / 2 with the entropy S = sum(p - m - p ln(p / m)) relative to
+// a flat default m. alpha is chosen so that chi^2 equals the number of data points.
+//
+// See B. D. Rainford and G. J. Daniell, Hyperfine Interactions 87, 1129 (1994)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxEnt {
+    pub detectors: Vec<MaxEntDetector>,
+    pub field: (f64, f64), // field range in G
+    pub bins: usize,       // number of field bins
+    pub range: (f64, f64), // time window in µs
+    pub packing: usize,
+    pub background: Background,
+    pub frames: f64, // number of frames (pulses or good muons) the histograms were accumulated over
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDistribution {
+    pub field: Vec<f64>,       // bin centers in G
+    pub probability: Vec<f64>, // p(B) normalized to 1, in 1/G
+    pub error: Vec<f64>, // 1 sigma confidence of every bin from the curvature of the posterior
+    pub asymmetry: f64,  // total asymmetry of the distribution
+    pub alpha: f64,      // regularization
+    pub chi_square: f64,
+    pub points: usize,
+}
+
+impl MaxEntDetector {
+    pub fn new(histogram: i64) -> MaxEntDetector {
+        MaxEntDetector {
+            histogram,
+            phase: None,
+            dead_time: None,
+        }
+    }
+
+    fn phase(&self, detector: &Detector) -> f64 {
+        self.phase
+            .or_else(|| header_number(detector, "Phase"))
+            .unwrap_or(0.0)
+    }
+
+    fn dead_time(&self, detector: &Detector) -> f64 {
+        self.dead_time
+            .or_else(|| header_number(detector, "Dead Time"))
+            .unwrap_or(0.0)
+    }
+}
+
+impl FieldDistribution {
+    // Mean field in G
+    pub fn mean(&self) -> f64 {
+        let width = self.bin_width();
+        self.field
+            .iter()
+            .zip(&self.probability)
+            .map(|(field, p)| field * p * width)
+            .sum()
+    }
+
+    // Standard deviation of the field in G
+    pub fn width(&self) -> f64 {
+        let (mean, width) = (self.mean(), self.bin_width());
+        self.field
+            .iter()
+            .zip(&self.probability)
+            .map(|(field, p)| (field - mean).powi(2) * p * width)
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn bin_width(&self) -> f64 {
+        match self.field[..] {
+            [first, second, ..] => second - first,
+            _ => 1.0,
+        }
+    }
+}
+
+impl MusrRootFile {
+    pub fn max_ent(&self, max_ent: &MaxEnt) -> Result<FieldDistribution, AnalysisError> {
+        if max_ent.bins == 0 || max_ent.field.1 <= max_ent.field.0 {
+            return Err(AnalysisError::InvalidSpectrum("Empty field range".into()));
+        }
+        let bin_width = (max_ent.field.1 - max_ent.field.0) / max_ent.bins as f64;
+        let field: Vec<f64> = (0..max_ent.bins)
+            .map(|k| max_ent.field.0 + (k as f64 + 0.5) * bin_width)
+            .collect();
+        // Angular frequencies in rad/µs
+        let omega: Vec<f64> = field
+            .iter()
+            .map(|field| 2.0 * PI * MUON_GYROMAGNETIC_RATIO * field * 1e-4)
+            .collect();
+
+        // Kernel rows, data and weights of all detectors
+        let mut kernel: Vec<Vec<f64>> = vec![];
+        let mut data = vec![];
+        let mut weights = vec![];
+        let detector_info = &self.run_header.detector_info;
+        for settings in &max_ent.detectors {
+            let h_decay = self
+                .histos
+                .decay_ana_module
+                .h_decay
+                .iter()
+                .find(|h_decay| h_decay.number == settings.histogram)
+                .ok_or(AnalysisError::MissingHistogram(settings.histogram))?;
+            let detector = h_decay
+                .detector(detector_info)
+                .ok_or(AnalysisError::MissingDetector(settings.histogram))?;
+            let dead_time_corrected = dead_time_corrected(
+                &h_decay.contents,
+                settings.dead_time(detector),
+                h_decay.bin_width,
+                max_ent.frames,
+            );
+            // Saturated bins keep their raw counts for the background and N0 and are weighted out below
+            let contents: Vec<f64> = dead_time_corrected
+                .iter()
+                .zip(&h_decay.contents)
+                .map(|(corrected, raw)| corrected.unwrap_or(*raw))
+                .collect();
+            let background = estimate(&contents, detector.t0_bin()?, &max_ent.background)?;
+            let good_bins = detector.good_bins(contents.len());
+            let times = h_decay.good_times(detector)?;
+            let packing = Packing::Fixed(max_ent.packing);
+            let saturated: Vec<bool> = packing
+                .ranges(&times)
+                .into_iter()
+                .map(|range| {
+                    dead_time_corrected[good_bins.start..][range]
+                        .iter()
+                        .any(Option::is_none)
+                })
+                .collect();
+            let corrected = pack(&times, &contents[good_bins], &packing, background)
+                .lifetime_corrected(MUON_LIFETIME);
+
+            let phase = settings.phase(detector).to_radians();
+            let first = data.len();
+            for (i, t) in corrected.time.iter().enumerate() {
+                if *t < max_ent.range.0
+                    || *t > max_ent.range.1
+                    || corrected.error[i] <= 0.0
+                    || saturated[i]
+                {
+                    continue;
+                }
+                kernel.push(omega.iter().map(|w| (w * t + phase).cos()).collect());
+                data.push(corrected.value[i]);
+                weights.push(corrected.error[i].powi(-2));
+            }
+
+            // N0 estimated from the data is off when the asymmetry does not average out, which shifts the
+            // spectrum by a constant. The weighted mean of every detector is removed from data and model,
+            // which is the same as fitting a free offset per detector.
+            let weight: f64 = weights[first..].iter().sum();
+            if weight > 0.0 {
+                let mean = |values: &mut dyn Iterator<Item = f64>| {
+                    values
+                        .zip(&weights[first..])
+                        .map(|(v, w)| v * w)
+                        .sum::<f64>()
+                        / weight
+                };
+                let data_mean = mean(&mut data[first..].iter().copied());
+                for value in &mut data[first..] {
+                    *value -= data_mean;
+                }
+                for k in 0..omega.len() {
+                    let kernel_mean = mean(&mut kernel[first..].iter().map(|row| row[k]));
+                    for row in &mut kernel[first..] {
+                        row[k] -= kernel_mean;
+                    }
+                }
+            }
+        }
+        let points = data.len();
+        if points == 0 {
+            return Err(AnalysisError::InvalidSpectrum(
+                "No data points in the time window".into(),
+            ));
+        }
+
+        // chi^2(p) = p^T M p - 2 p^T b + c
+        let bins = max_ent.bins;
+        let mut m = vec![vec![0.0; bins]; bins];
+        let mut b = vec![0.0; bins];
+        for ((row, y), w) in kernel.iter().zip(&data).zip(&weights) {
+            for ((b, m), r) in b.iter_mut().zip(&mut m).zip(row) {
+                *b += r * w * y;
+                for (m, s) in m.iter_mut().zip(row) {
+                    *m += r * w * s;
+                }
+            }
+        }
+        let c: f64 = data.iter().zip(&weights).map(|(y, w)| w * y * y).sum();
+        let chi_square = |p: &[f64]| {
+            let mut sum = c;
+            for k in 0..bins {
+                sum += p[k] * ((0..bins).map(|l| m[k][l] * p[l]).sum::<f64>() - 2.0 * b[k]);
+            }
+            sum
+        };
+
+        // Flat default holding 1 % of the asymmetry of the early data, low enough that the empty parts of
+        // the field range do not broaden the distribution
+        let early = points.min(20);
+        let amplitude = (2.0 * data[..early].iter().map(|y| y * y).sum::<f64>() / early as f64)
+            .sqrt()
+            .max(1e-3);
+        let default = 0.01 * amplitude / bins as f64;
+        let solver = Solver {
+            m: &m,
+            b: &b,
+            default,
+        };
+
+        // Lower alpha until chi^2 reaches the number of points, then bisect on log(alpha)
+        let trace: f64 = (0..bins).map(|k| m[k][k]).sum();
+        let mut alpha = trace * default;
+        let mut p = vec![default; bins];
+        let mut upper = alpha;
+        let mut lower = None;
+        for _ in 0..60 {
+            p = solver.solve(alpha, p);
+            if chi_square(&p) <= points as f64 {
+                lower = Some(alpha);
+                break;
+            }
+            upper = alpha;
+            alpha /= 4.0;
+        }
+        // Without reaching it the distribution under-fits the data, e.g. for a field range missing the signal
+        let Some(mut lower) = lower else {
+            return Err(AnalysisError::InvalidSpectrum(format!(
+                "chi^2 = {:.1} does not reach the {} data points",
+                chi_square(&p),
+                points
+            )));
+        };
+        for _ in 0..20 {
+            alpha = (lower * upper).sqrt();
+            let trial = solver.solve(alpha, p.clone());
+            if chi_square(&trial) <= points as f64 {
+                lower = alpha;
+            } else {
+                upper = alpha;
+                p = trial;
+            }
+        }
+        alpha = lower;
+        p = solver.solve(alpha, p);
+
+        // Curvature of the posterior alpha diag(1 / p) + M
+        let mut curvature = m.clone();
+        for k in 0..bins {
+            curvature[k][k] += alpha / p[k];
+        }
+        let covariance = invert(curvature).ok_or_else(|| {
+            AnalysisError::InvalidSpectrum("Singular curvature of the posterior".into())
+        })?;
+
+        let asymmetry: f64 = p.iter().sum();
+        let norm = asymmetry * bin_width;
+        Ok(FieldDistribution {
+            probability: p.iter().map(|p| p / norm).collect(),
+            error: (0..bins)
+                .map(|k| covariance[k][k].max(0.0).sqrt() / norm)
+                .collect(),
+            field,
+            asymmetry,
+            alpha,
+            chi_square: chi_square(&p),
+            points,
+        })
+    }
+}
+
+struct Solver<'a> {
+    m: &'a [Vec<f64>],
+    b: &'a [f64],
+    default: f64,
+}
+
+impl Solver<'_> {
+    // Maximize alpha S - chi^2 / 2 with Newton steps, starting at `p`
+    fn solve(&self, alpha: f64, mut p: Vec<f64>) -> Vec<f64> {
+        let bins = p.len();
+        let objective = |p: &[f64]| {
+            let entropy: f64 = p
+                .iter()
+                .map(|p| p - self.default - p * (p / self.default).ln())
+                .sum();
+            let mut chi_square = 0.0;
+            for k in 0..bins {
+                chi_square +=
+                    p[k] * ((0..bins).map(|l| self.m[k][l] * p[l]).sum::<f64>() - 2.0 * self.b[k]);
+            }
+            alpha * entropy - chi_square / 2.0
+        };
+        let mut q = objective(&p);
+        for _ in 0..MAX_ITERATIONS {
+            let gradient: Vec<f64> = (0..bins)
+                .map(|k| {
+                    -alpha * (p[k] / self.default).ln()
+                        - ((0..bins).map(|l| self.m[k][l] * p[l]).sum::<f64>() - self.b[k])
+                })
+                .collect();
+            let mut hessian = self.m.to_vec();
+            for k in 0..bins {
+                hessian[k][k] += alpha / p[k];
+            }
+            let Some(inverse) = invert(hessian) else {
+                break;
+            };
+            let step: Vec<f64> = inverse
+                .iter()
+                .map(|row| row.iter().zip(&gradient).map(|(h, g)| h * g).sum())
+                .collect();
+
+            // Keep p positive and shorten the step until the objective increases
+            let mut length = 1.0;
+            let mut improved = false;
+            while length > 1e-6 {
+                let trial: Vec<f64> = p
+                    .iter()
+                    .zip(&step)
+                    .map(|(p, step)| (p + length * step).max(0.1 * p))
+                    .collect();
+                let trial_q = objective(&trial);
+                if trial_q >= q {
+                    improved = trial_q - q > 1e-10 * q.abs().max(1.0);
+                    p = trial;
+                    q = trial_q;
+                    break;
+                }
+                length /= 2.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+        p
+    }
+}
+
+// Non-paralyzable dead time correction N / (1 - N dead_time / (frames bin_width)), times in ns. None for
+// saturated bins, which lose more than MAX_DEAD_TIME_LOSS of their counts and cannot be corrected reliably.
+fn dead_time_corrected(
+    contents: &[f64],
+    dead_time: f64,
+    bin_width: f64,
+    frames: f64,
+) -> Vec<Option<f64>> {
+    if dead_time <= 0.0 || frames <= 0.0 {
+        return contents.iter().copied().map(Some).collect();
+    }
+    contents
+        .iter()
+        .map(|count| {
+            let lost = count * dead_time / (frames * bin_width);
+            (lost <= MAX_DEAD_TIME_LOSS).then(|| count / (1.0 - lost))
+        })
+        .collect()
+}
+
+fn header_number(detector: &Detector, label: &str) -> Option<f64> {
+    match detector.extra.get(label)?.first()? {
+        HeaderValue::Double(value) => Some(*value),
+        HeaderValue::Int(value) => Some(*value as f64),
+        HeaderValue::PhysicalQuantity(quantity) => Some(quantity.value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    // Deterministic noise with mean 0 and variance 1
+    fn noise(i: usize) -> f64 {
+        let x = (i as u64 + 1).wrapping_mul(6364136223846793005) >> 11;
+        3f64.sqrt() * (2.0 * x as f64 / (1u64 << 53) as f64 - 1.0)
+    }
+
+    #[test]
+    fn dead_time() {
+        let contents = [0.0, 100.0, 1000.0, 1500.0];
+        assert_eq!(
+            dead_time_corrected(&contents, 0.0, 1.0, 10.0),
+            contents.map(Some)
+        );
+        // 1000 counts in 10 frames of a 1 ns bin with 0.005 ns dead time lose 50 %
+        let corrected = dead_time_corrected(&contents, 0.005, 1.0, 10.0);
+        assert_eq!(corrected[0], Some(0.0));
+        assert!((corrected[2].unwrap() - 2000.0).abs() < 1e-9);
+        assert!(corrected[1].unwrap() > 100.0 && corrected[1].unwrap() < 106.0);
+        // 1500 counts lose 75 %, the bin is saturated
+        assert_eq!(corrected[3], None);
+    }
+
+    #[tokio::test]
+    async fn gaussian_field_distribution() {
+        // Four detectors at 90° to each other, Gaussian distribution at 100 G with a width of 5 G
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        let gamma = 2.0 * PI * MUON_GYROMAGNETIC_RATIO * 1e-4; // rad/µs/G
+        let h_decay = &mut musr_root_file.histos.decay_ana_module.h_decay;
+        for (index, phase) in [0.0, 90.0, 180.0, 270.0f64].iter().enumerate() {
+            let bin_width = h_decay[index].bin_width / 1000.0;
+            for (bin, count) in h_decay[index].contents.iter_mut().enumerate() {
+                let t = (bin as f64 - 2834.0) * bin_width;
+                *count = if t < 0.0 {
+                    5.0
+                } else {
+                    let asymmetry = 0.2
+                        * (-(gamma * 5.0 * t).powi(2) / 2.0).exp()
+                        * (gamma * 100.0 * t + phase.to_radians()).cos();
+                    let expected = 2000.0 * (-t / MUON_LIFETIME).exp() * (1.0 + asymmetry) + 5.0;
+                    expected + expected.sqrt() * noise(index * 100000 + bin)
+                };
+            }
+        }
+        // The phase of the last detector comes from the header
+        musr_root_file.run_header.detector_info.detectors[3]
+            .extra
+            .insert("Phase".into(), vec![HeaderValue::Double(270.0)]);
+
+        let max_ent = MaxEnt {
+            detectors: vec![
+                MaxEntDetector {
+                    phase: Some(0.0),
+                    ..MaxEntDetector::new(1)
+                },
+                MaxEntDetector {
+                    phase: Some(90.0),
+                    ..MaxEntDetector::new(2)
+                },
+                MaxEntDetector {
+                    phase: Some(180.0),
+                    ..MaxEntDetector::new(3)
+                },
+                MaxEntDetector::new(4),
+            ],
+            field: (50.0, 150.0),
+            bins: 100,
+            range: (0.0, 4.0),
+            packing: 50,
+            background: Background::PreT0,
+            frames: 1e6,
+        };
+        let distribution = musr_root_file.max_ent(&max_ent).unwrap();
+        assert_eq!(distribution.field.len(), 100);
+        assert_eq!(distribution.field[0], 50.5);
+        assert!(
+            (distribution.chi_square - distribution.points as f64).abs()
+                < 0.01 * distribution.points as f64
+        );
+        assert!((distribution.mean() - 100.0).abs() < 0.5);
+        assert!((distribution.width() - 5.0).abs() < 1.5);
+        assert!((distribution.asymmetry - 0.2).abs() < 0.02);
+        let sum: f64 = distribution.probability.iter().sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert!(distribution.error.iter().all(|error| *error > 0.0));
+
+        // No distribution within 200 G to 300 G describes the precession at 100 G
+        let outside = MaxEnt {
+            field: (200.0, 300.0),
+            ..max_ent.clone()
+        };
+        assert!(matches!(
+            musr_root_file.max_ent(&outside),
+            Err(AnalysisError::InvalidSpectrum(_))
+        ));
+
+        let mut missing = max_ent.clone();
+        missing.detectors.push(MaxEntDetector::new(999));
+        assert_eq!(
+            musr_root_file.max_ent(&missing),
+            Err(AnalysisError::MissingHistogram(999))
+        );
+    }
+}
@@ -1,23 +1,37 @@
-use std::mem::size_of;
+use std::collections::BTreeMap;
 
-#[derive(Debug)]
+use root_io::core::{Folder, FolderItem};
+use serde::Serialize;
+
+use crate::header_entry::{HeaderEntry, HeaderValue};
+use crate::physical_quantity::PhysicalQuantity;
+use crate::slow_control::SlowControlHisto;
+
+#[derive(Debug, Serialize)]
 pub struct MusrRootFile {
     pub histos: Histos,
     pub run_header: RunHeader,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Histos {
-    pub decay_ana_module: DecayAnaModule,
-    pub sc_ana_module: SCAnaModule,
+    pub decay_ana_module: DecayAnaModule, // empty if the folder is missing, see `folders`
+    pub sc_ana_module: SCAnaModule,       // empty if the folder is missing, see `folders`
+    pub folders: BTreeMap<String, Vec<HistoEntry>>, // content of all sub-folders as found in the file
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoEntry {
+    pub name: String,       // e.g. hDecay001
+    pub class_name: String, // ROOT class, e.g. TH1F
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct DecayAnaModule {
     pub h_decay: Vec<HDecay>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct HDecay {
     // Here it is assumed that there are hypothetical red / green data with electric field on/off
     //  and light on/off, and hence 4 data sets per detector, and 8 detectors of the instrument:
@@ -36,15 +50,17 @@ pub struct HDecay {
     // hDecay012 # top/forward, electric field on, light off
     //
     // Check PSI doc link in the README file for more information.
-    pub field1: f64,
-    pub field2: f64,
-    pub field3: f64,
+    pub name: String,            // histogram name, e.g. hDecay001
+    pub number: i64,             // histogram number, e.g. 1 for hDecay001 or 21 for hDecay021
+    pub contents: Vec<f64>,      // bin contents without under- and overflow
+    pub bin_width: f64, // in ns, derived from Time Resolution; 0 if the run header does not define it
+    pub detector: Option<usize>, // index into `RunHeader::detector_info.detectors` of the matching detector
+    pub red_green_offset: i64, // offset of the red / green mode the histogram belongs to, see `DecayAnaModule::mode`
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct SCAnaModule {
-    pub h_sample_temperature: f64,
-    pub h_sample_magnetic_field: f64,
+    pub histos: Vec<SlowControlHisto>, // slow control histograms, e.g. Sample Temperature, Moderator HV
 }
 
 // In the RunHeader (except for the last part of it, the RunSummary, all fields follow this rule: <number> - <label>: <value> -@<type>.
@@ -59,13 +75,19 @@ pub struct SCAnaModule {
 // 0002 -
 // 0003 - LCO, T=170.02(K), wTF ~30(G)/5.18(A), Tr/Sa=15.02/8.50(kV), E=5.63(keV), LEDb off, BP off
 // 0004 - =========================================================================================
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct RunHeader {
     pub run_info: RunInfo,
     pub detector_info: DetectorInfo,
     pub sample_environment_info: SampleEnvironmentInfo,
     pub magnetic_field_environment_info: MagneticFieldEnvironmentInfo,
     pub beamline_info: BeamlineInfo,
+    pub run_summary: Vec<String>, // lines of the RunSummary without their line number
+    #[serde(skip)]
+    pub(crate) folders: Vec<String>, // names of the sub-folders as found in the file
+    #[serde(skip)]
+    pub(crate) entries: BTreeMap<String, Vec<HeaderEntry>>, // raw entries as found in the file, see `entries()`
+    pub extra: BTreeMap<String, Extra>, // additional sub-folders by name, e.g. ScalerInfo
 }
 
 // TMusrRunPhysicalQuantity is a data type (represents Physical quantities) that can be described as: <property name>: <value> +- <estimated error> <unit>; SP: <demand>; <description>. It can also contain various representations.
@@ -78,365 +100,708 @@ pub struct RunHeader {
 // TDoubleVector is a collection of floating point numbers.
 //
 // Check link for documentation ("TMusrRunHeader Concept" section): https://lmu.web.psi.ch/musrfit/user/html/musr-root.html
-#[derive(Debug)]
+//
+// Missing or malformed mandatory entries are filled with default values; use `MusrRootFile::validate` to find them.
+//
+// Every header section may carry additional, instrument specific entries (`xs:any` in MusrRoot.xsd).
+// They are kept by label in `extra`, as are known entries of the wrong type and lines which are no entries.
+// A label may repeat, so every label holds all of its values in the order of the file.
+#[derive(Debug, Default, Serialize)]
 pub struct RunInfo {
     pub version: String,                // Git version of `TMusrRunHeader`
     pub generic_validator_url: String,  // URL
     pub specific_validator_url: String, // URL
     pub generator: String,              // program which wrote the MusrRoot file e.g., nemu_analyzer
-    pub file_name: String, // file name of the MusrRoot file e.g., deltat_tdc_gps_4295.root
+    pub proposal_number: Option<i64>,
+    pub main_proposer: Vec<String>, // there might be several main proposers
+    pub file_name: String,          // file name of the MusrRoot file e.g., deltat_tdc_gps_4295.root
     pub run_title: String,
     pub run_number: i64,
-    pub run_start_time: String, // ISO 8601 date time
-    pub run_stop_time: String,  // ISO 8601 date time
-    // TODO: Missing `Run Duration` attribute of type TMusrRunPhysicalQuantity. e.g., "run duration in sec"
-    pub laboratory: String, // e.g., PSI
-    pub instrument: String, // e.g., GPS
-    // TODO: Missing `Muon Beam Momentum` attribute of type TMusrRunPhysicalQuantity e.g. "28.1 MeV/c"
-    pub muon_species: String, //  positive or negative muon
-    pub muon_source: String,  // e.g. “Target E - Low Energy Muons” or “Target M” …
+    pub run_start_time: String,                       // ISO 8601 date time
+    pub run_stop_time: String,                        // ISO 8601 date time
+    pub run_duration: Option<PhysicalQuantity>,       // run duration in sec
+    pub laboratory: String,                           // e.g., PSI
+    pub instrument: String,                           // e.g., GPS
+    pub muon_beam_momentum: Option<PhysicalQuantity>, // e.g. 28.1 MeV/c
+    pub muon_species: String,                         //  positive or negative muon
+    pub muon_source: String, // e.g. “Target E - Low Energy Muons” or “Target M” …
     pub setup: String,
     pub comment: String,
     pub sample_name: String,
-    // TODO: Missing `Sample Temperature` attribute of type TMusrRunPhysicalQuantity e.g. 3.21 +- 0.05 K; SP: 3.2; CF1
-    // TODO: Missing `Sample Magnetic Field` attribute of type TMusrRunPhysicalQuantity e.g. 350.002 +- 0.005 G; SP: 350; WEW
+    pub sample_temperature: Option<PhysicalQuantity>, // e.g. 3.21 +- 0.05 K; SP: 3.2; CF1
+    pub sample_magnetic_field: Option<PhysicalQuantity>, // e.g. 350.002 +- 0.005 G; SP: 350; WEW
     pub no_of_histos: i64,
-    // TODO: Missing `Time Resolution` attribute of type TMusrRunPhysicalQuantity e.g. 0.1953125 ns
-    // TODO: Missing `RedGreen Offsets` attribute of type TIntVector e.g. 0; 20
+    pub time_resolution: Option<PhysicalQuantity>, // e.g. 0.1953125 ns; TDC CAEN V1190
+    pub red_green_offsets: Vec<i64>,               // e.g. 0; 20
+    pub extra: Extra,                              // e.g. Moderator HV at LEM
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct DetectorInfo {
     pub detectors: Vec<Detector>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Detector {
-    pub name: String,       // detector name, e.g. Left-NPP
+    pub folder_name: String, // name of the sub-folder in DetectorInfo, e.g. Detector001
+    pub name: String,        // detector name, e.g. Left-NPP
     pub histo_number: i64, // histogram number. This number corresponds to the histogram number in the histos/DecayAnaModule sub-tree.
     pub histo_length: i64, // length of the histogram (in bins)
-    pub time_zero_bin: f64, // The type is Double_t since for the high-field spectrometer at PSI an Int_t representation would be not good enough.
+    pub time_zero_bin: Option<f64>, // The type is Double_t since for the high-field spectrometer at PSI an Int_t representation would be not good enough.
     pub first_good_bin: i64,
     pub last_good_bin: i64,
+    pub extra: Extra,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct SampleEnvironmentInfo {
     pub cryo: String, // name of the used cryostat/oven, e.g. Konti-2
+    pub extra: Extra, // e.g. Insert, Orientation or cryostat temperatures
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct MagneticFieldEnvironmentInfo {
     pub magnet_name: String, // name of the used magnet, e.g. WEW. In case of ZF measurements, there might be an entry like ZF.
+    pub extra: Extra,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize)]
 pub struct BeamlineInfo {
     pub name: String, // name of the beamline, e.g. piM3.2
+    pub extra: Extra, // e.g. Beamline Settings
 }
 
 impl MusrRootFile {
-    pub fn parse(bytes: &[u8]) -> Option<MusrRootFile> {
-        let histos = Histos::parse(bytes)?;
-        let run_header = RunHeader::parse(bytes)?;
+    pub fn parse(histos: &Folder, run_header: &Folder) -> Option<MusrRootFile> {
+        let run_header = RunHeader::parse(run_header)?;
+        let histos = Histos::parse(histos, &run_header)?;
         Some(MusrRootFile { histos, run_header })
     }
 }
 
 impl Histos {
-    pub fn parse(bytes: &[u8]) -> Option<Histos> {
-        let decay_ana_module = DecayAnaModule::parse(bytes)?;
-        let sc_ana_module = SCAnaModule::parse(bytes)?;
+    pub fn parse(folder: &Folder, run_header: &RunHeader) -> Option<Histos> {
+        let decay_ana_module = folder
+            .get("DecayAnaModule")
+            .and_then(|folder| DecayAnaModule::parse(folder, run_header))
+            .unwrap_or_default();
+        let sc_ana_module = folder
+            .get("SCAnaModule")
+            .and_then(|folder| SCAnaModule::parse(folder, run_header))
+            .unwrap_or_default();
+        let folders = folder
+            .items
+            .iter()
+            .map(|sub_folder| {
+                let histograms = sub_folder
+                    .items()
+                    .iter()
+                    .map(|histogram| HistoEntry {
+                        name: histogram.name().to_string(),
+                        class_name: histogram.class_name().to_string(),
+                    })
+                    .collect();
+                (sub_folder.name().to_string(), histograms)
+            })
+            .collect();
+
         Some(Histos {
             decay_ana_module,
             sc_ana_module,
+            folders,
         })
     }
 }
 
 impl HDecay {
-    pub fn parse(bytes: &[u8]) -> Option<HDecay> {
-        if bytes.len() < size_of::<HDecay>() {
-            return None;
-        }
-
-        let field1_bytes = &bytes[0..8];
-        let field2_bytes = &bytes[8..16];
-        let field3_bytes: &[u8] = &bytes[16..24];
-
-        let field1 = f64::from_le_bytes(field1_bytes.try_into().unwrap());
-        let field2 = f64::from_le_bytes(field2_bytes.try_into().unwrap());
-        let field3 = f64::from_le_bytes(field3_bytes.try_into().unwrap());
+    pub fn parse(histogram: &FolderItem, run_header: &RunHeader) -> Option<HDecay> {
+        let histogram = match histogram {
+            FolderItem::Histogram1D(histogram) => histogram,
+            _ => return None,
+        };
+        let number = histogram.name.strip_prefix("hDecay")?.parse().ok()?;
+        let red_green_offsets = &run_header.run_info.red_green_offsets;
+        let detector = run_header.detector_info.find(number, red_green_offsets);
+        // The detector tells which offset was added; without one the largest offset giving a valid
+        // histogram number is assumed
+        let red_green_offset = match detector {
+            Some(index) => number - run_header.detector_info.detectors[index].histo_number,
+            None => red_green_offsets
+                .iter()
+                .copied()
+                .filter(|offset| *offset < number)
+                .max()
+                .unwrap_or_default(),
+        };
 
         Some(HDecay {
-            field1,
-            field2,
-            field3,
+            name: histogram.name.clone(),
+            number,
+            contents: histogram.bins().to_vec(),
+            bin_width: run_header.run_info.time_resolution_ns().unwrap_or_default(),
+            detector,
+            red_green_offset,
         })
     }
+
+    // Number of the histogram within its red / green mode, i.e. the Histo Number of its detector
+    pub fn histo_number(&self) -> i64 {
+        self.number - self.red_green_offset
+    }
+
+    pub fn detector<'a>(&self, detector_info: &'a DetectorInfo) -> Option<&'a Detector> {
+        detector_info.detectors.get(self.detector?)
+    }
 }
 
 impl DecayAnaModule {
-    pub fn parse(bytes: &[u8]) -> Option<DecayAnaModule> {
-        let h_decay_size = size_of::<HDecay>();
-        if bytes.len() < h_decay_size {
-            return None;
-        }
+    pub fn parse(folder: &FolderItem, run_header: &RunHeader) -> Option<DecayAnaModule> {
+        let h_decay = folder
+            .items()
+            .iter()
+            .filter_map(|histogram| HDecay::parse(histogram, run_header))
+            .collect();
+
+        Some(DecayAnaModule { h_decay })
+    }
 
-        let num_h_decays = bytes.len() / h_decay_size;
-        let mut h_decays = Vec::with_capacity(num_h_decays);
+    // Offsets of the red / green modes present in the file in ascending order, e.g. [0, 40]
+    pub fn red_green_offsets(&self) -> Vec<i64> {
+        self.modes().into_keys().collect()
+    }
 
-        for i in 0..num_h_decays {
-            let start = i * h_decay_size;
-            let end = start + h_decay_size;
-            let h_decay_bytes = &bytes[start..end];
+    // Histograms of one red / green mode ordered by Histo Number, e.g. `mode(10)` for electric field on,
+    // light off in the example above. The lists of different modes therefore line up detector by detector.
+    pub fn mode(&self, red_green_offset: i64) -> Vec<&HDecay> {
+        self.modes().remove(&red_green_offset).unwrap_or_default()
+    }
 
-            if let Some(h_decay) = HDecay::parse(h_decay_bytes) {
-                h_decays.push(h_decay);
-            } else {
-                return None;
-            }
+    // All histograms split by red / green offset
+    pub fn modes(&self) -> BTreeMap<i64, Vec<&HDecay>> {
+        let mut modes: BTreeMap<i64, Vec<&HDecay>> = BTreeMap::new();
+        for h_decay in &self.h_decay {
+            modes
+                .entry(h_decay.red_green_offset)
+                .or_default()
+                .push(h_decay);
         }
-
-        Some(DecayAnaModule { h_decay: h_decays })
+        for histograms in modes.values_mut() {
+            histograms.sort_by_key(|h_decay| h_decay.histo_number());
+        }
+        modes
     }
 }
 
 impl SCAnaModule {
-    pub fn parse(bytes: &[u8]) -> Option<SCAnaModule> {
-        let struct_size = size_of::<SCAnaModule>();
-        if bytes.len() < struct_size {
-            return None;
-        }
-
-        let temperature_bytes = &bytes[0..8];
-        let magnetic_field_bytes = &bytes[8..16];
+    pub fn parse(folder: &FolderItem, run_header: &RunHeader) -> Option<SCAnaModule> {
+        let histos = folder
+            .items()
+            .iter()
+            .filter_map(|histogram| match histogram {
+                FolderItem::Histogram1D(histogram) => Some(SlowControlHisto::from_histogram(
+                    histogram,
+                    &run_header.unit(&histogram.name),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        Some(SCAnaModule { histos })
+    }
 
-        let temperature = f64::from_le_bytes(temperature_bytes.try_into().unwrap());
-        let magnetic_field = f64::from_le_bytes(magnetic_field_bytes.try_into().unwrap());
+    pub fn get(&self, name: &str) -> Option<&SlowControlHisto> {
+        self.histos.iter().find(|histo| histo.name == name)
+    }
 
-        Some(SCAnaModule {
-            h_sample_temperature: temperature,
-            h_sample_magnetic_field: magnetic_field,
-        })
+    pub fn histo_names(&self) -> Vec<&str> {
+        self.histos
+            .iter()
+            .map(|histo| histo.name.as_str())
+            .collect()
     }
 }
 
 impl RunHeader {
-    pub fn parse(bytes: &[u8]) -> Option<RunHeader> {
-        let header_size = size_of::<RunHeader>();
-        if bytes.len() < header_size {
-            return None;
-        }
-
-        let run_info_size = size_of::<RunInfo>();
-        let detector_info_size = size_of::<DetectorInfo>();
-        let sample_env_info_size = size_of::<SampleEnvironmentInfo>();
-        let mag_field_env_info_size = size_of::<MagneticFieldEnvironmentInfo>();
-        let beamline_info_size = size_of::<BeamlineInfo>(); // TODO: review! var is not being used
-
-        let run_info = RunInfo::parse(&bytes[0..run_info_size])?;
-        let detector_info =
-            DetectorInfo::parse(&bytes[run_info_size..run_info_size + detector_info_size])?;
-        let sample_env_info = SampleEnvironmentInfo::parse(
-            &bytes[run_info_size + detector_info_size
-                ..run_info_size + detector_info_size + sample_env_info_size],
-        )?;
-        let mag_field_env_info = MagneticFieldEnvironmentInfo::parse(
-            &bytes[run_info_size + detector_info_size + sample_env_info_size
-                ..run_info_size
-                    + detector_info_size
-                    + sample_env_info_size
-                    + mag_field_env_info_size],
+    pub fn parse(folder: &Folder) -> Option<RunHeader> {
+        let run_info = section(folder, "RunInfo", RunInfo::parse)?;
+        let detector_info = section(folder, "DetectorInfo", DetectorInfo::parse)?;
+        let sample_environment_info = section(
+            folder,
+            "SampleEnvironmentInfo",
+            SampleEnvironmentInfo::parse,
         )?;
-        let beamline_info = BeamlineInfo::parse(
-            &bytes[run_info_size
-                + detector_info_size
-                + sample_env_info_size
-                + mag_field_env_info_size..],
+        let magnetic_field_environment_info = section(
+            folder,
+            "MagneticFieldEnvironmentInfo",
+            MagneticFieldEnvironmentInfo::parse,
         )?;
+        let beamline_info = section(folder, "BeamlineInfo", BeamlineInfo::parse)?;
+        let run_summary = folder
+            .get("RunSummary")
+            .map(run_summary)
+            .unwrap_or_default();
+        let mut entries = BTreeMap::new();
+        for item in folder
+            .items
+            .iter()
+            .filter(|item| item.name() != "RunSummary")
+        {
+            collect_raw_entries(item, item.name(), &mut entries);
+        }
+        let extra = folder
+            .items
+            .iter()
+            .filter(|item| !RUN_HEADER_FOLDERS.contains(&item.name()))
+            .map(|item| (item.name().to_string(), extra(header_entries(item))))
+            .collect();
 
         Some(RunHeader {
             run_info,
             detector_info,
-            sample_environment_info: sample_env_info,
-            magnetic_field_environment_info: mag_field_env_info,
+            sample_environment_info,
+            magnetic_field_environment_info,
             beamline_info,
+            run_summary,
+            folders: folder
+                .items
+                .iter()
+                .map(|item| item.name().to_string())
+                .collect(),
+            entries,
+            extra,
         })
     }
 }
 
-impl RunInfo {
-    pub fn parse(bytes: &[u8]) -> Option<RunInfo> {
-        let struct_size = size_of::<RunInfo>();
-        if bytes.len() < struct_size {
-            return None;
-        }
+impl RunHeader {
+    // Raw entries by sub-folder as found in the file, e.g. RunInfo or DetectorInfo/Detector001, which are
+    // needed to check and export the file itself. All values are to be taken from the typed sections.
+    pub fn entries(&self) -> &BTreeMap<String, Vec<HeaderEntry>> {
+        &self.entries
+    }
 
-        let version = String::from_utf8_lossy(&bytes[0..16])
-            .trim_end_matches('\0')
-            .to_string();
-        let generic_validator_url = String::from_utf8_lossy(&bytes[16..32])
-            .trim_end_matches('\0')
-            .to_string();
-        let specific_validator_url = String::from_utf8_lossy(&bytes[32..48])
-            .trim_end_matches('\0')
-            .to_string();
-        let generator = String::from_utf8_lossy(&bytes[48..64])
-            .trim_end_matches('\0')
-            .to_string();
-        let file_name = String::from_utf8_lossy(&bytes[64..80])
-            .trim_end_matches('\0')
-            .to_string();
-        let run_title = String::from_utf8_lossy(&bytes[80..96])
-            .trim_end_matches('\0')
-            .to_string();
-        let run_number = i64::from_le_bytes(bytes[96..104].try_into().unwrap());
-        let run_start_time = String::from_utf8_lossy(&bytes[104..120])
-            .trim_end_matches('\0')
-            .to_string();
-        let run_stop_time = String::from_utf8_lossy(&bytes[120..136])
-            .trim_end_matches('\0')
-            .to_string();
-        let laboratory = String::from_utf8_lossy(&bytes[136..152])
-            .trim_end_matches('\0')
-            .to_string();
-        let instrument = String::from_utf8_lossy(&bytes[152..168])
-            .trim_end_matches('\0')
-            .to_string();
-        let muon_species = String::from_utf8_lossy(&bytes[176..192])
-            .trim_end_matches('\0')
-            .to_string();
-        let muon_source = String::from_utf8_lossy(&bytes[192..208])
-            .trim_end_matches('\0')
-            .to_string();
-        let setup = String::from_utf8_lossy(&bytes[208..224])
-            .trim_end_matches('\0')
-            .to_string();
-        let comment = String::from_utf8_lossy(&bytes[224..240])
-            .trim_end_matches('\0')
-            .to_string();
-        let sample_name = String::from_utf8_lossy(&bytes[240..256])
-            .trim_end_matches('\0')
-            .to_string();
-        let no_of_histos = i64::from_le_bytes(bytes[256..264].try_into().unwrap());
+    // Unit of the physical quantity entry with the given label in any section, e.g. K for Sample Temperature
+    pub fn unit(&self, label: &str) -> String {
+        self.quantity(label)
+            .map(|quantity| quantity.unit)
+            .unwrap_or_default()
+    }
 
-        Some(RunInfo {
-            version,
-            generic_validator_url,
-            specific_validator_url,
-            generator,
-            file_name,
-            run_title,
-            run_number,
-            run_start_time,
-            run_stop_time,
-            laboratory,
-            instrument,
-            muon_species,
-            muon_source,
-            setup,
-            comment,
-            sample_name,
-            no_of_histos,
+    // Physical quantity entry with the given label in any section, e.g. Sample Temperature
+    pub fn quantity(&self, label: &str) -> Option<PhysicalQuantity> {
+        let run_info = &self.run_info;
+        let quantity = match label {
+            "Run Duration" => &run_info.run_duration,
+            "Muon Beam Momentum" => &run_info.muon_beam_momentum,
+            "Sample Temperature" => &run_info.sample_temperature,
+            "Sample Magnetic Field" => &run_info.sample_magnetic_field,
+            "Time Resolution" => &run_info.time_resolution,
+            _ => &None,
+        };
+        let extras = [
+            &run_info.extra,
+            &self.sample_environment_info.extra,
+            &self.magnetic_field_environment_info.extra,
+            &self.beamline_info.extra,
+        ]
+        .into_iter()
+        .chain(self.detector_info.detectors.iter().map(|d| &d.extra))
+        .chain(self.extra.values());
+        quantity.clone().or_else(|| {
+            extras
+                .filter_map(|extra| extra.get(label)?.first()?.as_physical_quantity())
+                .next()
+                .cloned()
         })
     }
 }
 
-impl DetectorInfo {
-    pub fn parse(bytes: &[u8]) -> Option<DetectorInfo> {
-        let struct_size = size_of::<DetectorInfo>();
-        if bytes.len() < struct_size {
-            return None;
-        }
+const RUN_HEADER_FOLDERS: [&str; 6] = [
+    "RunInfo",
+    "DetectorInfo",
+    "SampleEnvironmentInfo",
+    "MagneticFieldEnvironmentInfo",
+    "BeamlineInfo",
+    "RunSummary",
+];
+
+// A mandatory sub-folder of the RunHeader, empty if it is missing; `MusrRootFile::validate` reports that
+fn section<T: Default>(
+    folder: &Folder,
+    name: &str,
+    parse: impl Fn(&FolderItem) -> Option<T>,
+) -> Option<T> {
+    match folder.get(name) {
+        Some(item) => parse(item),
+        None => Some(T::default()),
+    }
+}
 
-        let detector_size = size_of::<Detector>();
-        let num_detectors = bytes.len() / detector_size;
-        let mut detectors = Vec::with_capacity(num_detectors);
-
-        for i in 0..num_detectors {
-            let start = i * detector_size;
-            let end = start + detector_size;
-            let detector_bytes = &bytes[start..end];
-            if let Some(detector) = Detector::parse(detector_bytes) {
-                detectors.push(detector);
-            } else {
-                return None;
+// RunSummary lines are of the form `<number> <text>`
+fn run_summary(folder: &FolderItem) -> Vec<String> {
+    folder
+        .items()
+        .iter()
+        .map(|line| {
+            let line = line.name().trim_end();
+            match line.split_once(' ') {
+                Some((_number, text)) => text.to_string(),
+                None => String::new(),
             }
+        })
+        .collect()
+}
+
+// Collect the entries of a RunHeader sub-folder and all of its sub-folders by path
+fn collect_raw_entries(
+    folder: &FolderItem,
+    path: &str,
+    entries: &mut BTreeMap<String, Vec<HeaderEntry>>,
+) {
+    let mut raw_entries = vec![];
+    for item in folder.items() {
+        match item {
+            FolderItem::ObjString(entry) => raw_entries.extend(HeaderEntry::parse(entry).ok()),
+            _ => collect_raw_entries(item, &format!("{}/{}", path, item.name()), entries),
         }
+    }
+    if !raw_entries.is_empty() {
+        entries.insert(path.to_string(), raw_entries);
+    }
+}
+
+// Entries of a RunHeader sub-folder as (label, value) in the order of the file. Labels may repeat, e.g. several
+// Main Proposer entries.
+type Entries = Vec<(String, HeaderValue)>;
+
+// Additional entries of a RunHeader sub-folder by label, with all values of a repeated label in file order
+pub type Extra = BTreeMap<String, Vec<HeaderValue>>;
+
+// Collect the `<number> - <label>: <value> -@<type>` entries of a RunHeader sub-folder.
+// Values which do not match their type tag are kept as String, lines which do not follow the format at all
+// are kept as String under the whole line.
+// The helpers below remove the entries they read if their type matches, so that all others end up in `extra`.
+fn header_entries(folder: &FolderItem) -> Entries {
+    folder
+        .items()
+        .iter()
+        .filter_map(|entry| match entry {
+            FolderItem::ObjString(line) => Some(match HeaderEntry::parse(line) {
+                Ok(entry) => {
+                    let value = entry
+                        .typed_value()
+                        .unwrap_or_else(|_| HeaderValue::String(entry.value.clone()));
+                    (entry.label, value)
+                }
+                Err(_) => (line.trim().to_string(), HeaderValue::String(line.clone())),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// Remove the first entry with the given label for which `value` gives a result
+fn take<T>(
+    entries: &mut Entries,
+    label: &str,
+    value: impl Fn(&HeaderValue) -> Option<T>,
+) -> Option<T> {
+    let (index, value) = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.0 == label)
+        .find_map(|(index, entry)| Some((index, value(&entry.1)?)))?;
+    entries.remove(index);
+    Some(value)
+}
+
+fn text(entries: &mut Entries, label: &str) -> String {
+    take(entries, label, |value| value.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+// All values of a label which may repeat or be a TStringVector
+fn texts(entries: &mut Entries, label: &str) -> Vec<String> {
+    let mut texts = vec![];
+    entries.retain(|(entry_label, value)| {
+        if entry_label != label {
+            return true;
+        }
+        match value {
+            HeaderValue::String(value) => texts.push(value.clone()),
+            HeaderValue::StringVector(values) => texts.extend(values.iter().cloned()),
+            _ => return true,
+        }
+        false
+    });
+    texts
+}
+
+fn quantity(entries: &mut Entries, label: &str) -> Option<PhysicalQuantity> {
+    take(entries, label, |value| {
+        value.as_physical_quantity().cloned()
+    })
+}
+
+fn int(entries: &mut Entries, label: &str) -> Option<i64> {
+    take(entries, label, HeaderValue::as_int)
+}
+
+fn ints(entries: &mut Entries, label: &str) -> Vec<i64> {
+    take(entries, label, |value| match value {
+        HeaderValue::IntVector(values) => Some(values.clone()),
+        HeaderValue::Int(value) => Some(vec![*value]),
+        _ => None,
+    })
+    .unwrap_or_default()
+}
+
+fn double(entries: &mut Entries, label: &str) -> Option<f64> {
+    take(entries, label, HeaderValue::as_double)
+}
+
+fn extra(entries: Entries) -> Extra {
+    let mut extra = Extra::new();
+    for (label, value) in entries {
+        extra.entry(label).or_default().push(value);
+    }
+    extra
+}
+
+impl RunInfo {
+    pub fn parse(folder: &FolderItem) -> Option<RunInfo> {
+        let mut entries = header_entries(folder);
+        let e = &mut entries;
+
+        Some(RunInfo {
+            version: text(e, "Version"),
+            generic_validator_url: text(e, "Generic Validator URL"),
+            specific_validator_url: text(e, "Specific Validator URL"),
+            generator: text(e, "Generator"),
+            proposal_number: int(e, "Proposal Number"),
+            main_proposer: texts(e, "Main Proposer"),
+            file_name: text(e, "File Name"),
+            run_title: text(e, "Run Title"),
+            run_number: int(e, "Run Number").unwrap_or_default(),
+            run_start_time: text(e, "Run Start Time"),
+            run_stop_time: text(e, "Run Stop Time"),
+            run_duration: quantity(e, "Run Duration"),
+            laboratory: text(e, "Laboratory"),
+            instrument: text(e, "Instrument"),
+            muon_beam_momentum: quantity(e, "Muon Beam Momentum"),
+            muon_species: text(e, "Muon Species"),
+            muon_source: text(e, "Muon Source"),
+            setup: text(e, "Setup"),
+            comment: text(e, "Comment"),
+            sample_name: text(e, "Sample Name"),
+            sample_temperature: quantity(e, "Sample Temperature"),
+            sample_magnetic_field: quantity(e, "Sample Magnetic Field"),
+            no_of_histos: int(e, "No of Histos").unwrap_or_default(),
+            time_resolution: quantity(e, "Time Resolution"),
+            red_green_offsets: ints(e, "RedGreen Offsets"),
+            extra: extra(entries),
+        })
+    }
+
+    // Time resolution, i.e. the bin width of the decay histograms, in ns
+    pub fn time_resolution_ns(&self) -> Option<f64> {
+        let time_resolution = self.time_resolution.as_ref()?;
+        let factor = match time_resolution.unit.as_str() {
+            "fs" => 1e-6,
+            "ps" => 1e-3,
+            "ns" => 1.0,
+            "us" | "µs" | "mus" => 1e3,
+            _ => return None,
+        };
+        Some(time_resolution.value * factor)
+    }
+}
+
+impl DetectorInfo {
+    pub fn parse(folder: &FolderItem) -> Option<DetectorInfo> {
+        let detectors = folder
+            .items()
+            .iter()
+            .map(Detector::parse)
+            .collect::<Option<Vec<_>>>()?;
 
         Some(DetectorInfo { detectors })
     }
+
+    // Index of the detector of histogram `hDecay<number>`. Histograms of the red / green modes are numbered
+    // Histo Number + offset, so the candidates are all detectors with Histo Number == number - offset.
+    // Several candidates are possible if a detector is listed once per mode (e.g. at LEM, where Detector041
+    // has Histo Number 1); then the one whose sub-folder carries the histogram number wins.
+    pub fn find(&self, number: i64, red_green_offsets: &[i64]) -> Option<usize> {
+        let folder_name = format!("Detector{:03}", number);
+        let candidates: Vec<usize> = self
+            .detectors
+            .iter()
+            .enumerate()
+            .filter(|(_, detector)| {
+                detector.histo_number == number
+                    || red_green_offsets
+                        .iter()
+                        .any(|offset| detector.histo_number == number - offset)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        candidates
+            .iter()
+            .find(|index| self.detectors[**index].folder_name == folder_name)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find(|index| self.detectors[**index].histo_number == number)
+            })
+            .or_else(|| candidates.first())
+            .copied()
+    }
 }
 
 impl Detector {
-    pub fn parse(bytes: &[u8]) -> Option<Detector> {
-        let struct_size = size_of::<Detector>();
-        if bytes.len() < struct_size {
-            return None;
-        }
-
-        let name = String::from_utf8_lossy(&bytes[0..16])
-            .trim_end_matches('\0')
-            .to_string();
-        let histo_number = i64::from_le_bytes(bytes[16..24].try_into().unwrap());
-        let histo_length = i64::from_le_bytes(bytes[24..32].try_into().unwrap());
-        let time_zero_bin = f64::from_le_bytes(bytes[32..40].try_into().unwrap());
-        let first_good_bin = i64::from_le_bytes(bytes[40..48].try_into().unwrap());
-        let last_good_bin = i64::from_le_bytes(bytes[48..56].try_into().unwrap());
+    pub fn parse(folder: &FolderItem) -> Option<Detector> {
+        let mut entries = header_entries(folder);
+        let e = &mut entries;
 
         Some(Detector {
-            name,
-            histo_number,
-            histo_length,
-            time_zero_bin,
-            first_good_bin,
-            last_good_bin,
+            folder_name: folder.name().to_string(),
+            name: text(e, "Name"),
+            histo_number: int(e, "Histo Number").unwrap_or_default(),
+            histo_length: int(e, "Histo Length").unwrap_or_default(),
+            time_zero_bin: double(e, "Time Zero Bin"),
+            first_good_bin: int(e, "First Good Bin").unwrap_or_default(),
+            last_good_bin: int(e, "Last Good Bin").unwrap_or_default(),
+            extra: extra(entries),
         })
     }
 }
 
 impl SampleEnvironmentInfo {
-    pub fn parse(bytes: &[u8]) -> Option<SampleEnvironmentInfo> {
-        let struct_size = size_of::<SampleEnvironmentInfo>();
-        if bytes.len() < struct_size {
-            return None;
-        }
+    pub fn parse(folder: &FolderItem) -> Option<SampleEnvironmentInfo> {
+        let mut entries = header_entries(folder);
 
-        let cryo = String::from_utf8_lossy(&bytes[0..16])
-            .trim_end_matches('\0')
-            .to_string();
+        Some(SampleEnvironmentInfo {
+            cryo: text(&mut entries, "Cryo"),
+            extra: extra(entries),
+        })
+    }
 
-        Some(SampleEnvironmentInfo { cryo })
+    // Instrument specific physical quantity, e.g. a cryostat temperature
+    pub fn quantity(&self, label: &str) -> Option<&PhysicalQuantity> {
+        self.extra.get(label)?.first()?.as_physical_quantity()
     }
 }
 
 impl MagneticFieldEnvironmentInfo {
-    pub fn parse(bytes: &[u8]) -> Option<MagneticFieldEnvironmentInfo> {
-        let struct_size = size_of::<MagneticFieldEnvironmentInfo>();
-        if bytes.len() < struct_size {
-            return None;
-        }
+    pub fn parse(folder: &FolderItem) -> Option<MagneticFieldEnvironmentInfo> {
+        let mut entries = header_entries(folder);
 
-        let magnet_name = String::from_utf8_lossy(&bytes[0..16])
-            .trim_end_matches('\0')
-            .to_string();
-
-        Some(MagneticFieldEnvironmentInfo { magnet_name })
+        Some(MagneticFieldEnvironmentInfo {
+            magnet_name: text(&mut entries, "Magnet Name"),
+            extra: extra(entries),
+        })
     }
 }
 
 impl BeamlineInfo {
-    pub fn parse(bytes: &[u8]) -> Option<BeamlineInfo> {
-        let struct_size = size_of::<BeamlineInfo>();
-        if bytes.len() < struct_size {
-            return None;
+    pub fn parse(folder: &FolderItem) -> Option<BeamlineInfo> {
+        let mut entries = header_entries(folder);
+
+        Some(BeamlineInfo {
+            name: text(&mut entries, "Name"),
+            extra: extra(entries),
+        })
+    }
+}
+
+// Histograms and detectors for the tests of the analysis modules
+#[cfg(test)]
+pub(crate) mod test_fixtures {
+    use super::*;
+
+    // hDecay001 of the first detector with the LEM bin width of 0.1953125 ns
+    pub(crate) fn h_decay(contents: Vec<f64>) -> HDecay {
+        HDecay {
+            name: "hDecay001".into(),
+            number: 1,
+            contents,
+            bin_width: 0.1953125,
+            detector: Some(0),
+            red_green_offset: 0,
         }
+    }
 
-        let name = String::from_utf8_lossy(&bytes[0..16])
-            .trim_end_matches('\0')
-            .to_string();
+    pub(crate) fn detector(
+        time_zero_bin: f64,
+        first_good_bin: i64,
+        last_good_bin: i64,
+    ) -> Detector {
+        Detector {
+            folder_name: "Detector001".into(),
+            name: "Left".into(),
+            histo_number: 1,
+            histo_length: last_good_bin + 1,
+            time_zero_bin: Some(time_zero_bin),
+            first_good_bin,
+            last_good_bin,
+            extra: Extra::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use root_io::core::Folder;
+
+    fn folder(name: &str, lines: &[&str]) -> FolderItem {
+        FolderItem::Folder(Folder {
+            name: name.to_string(),
+            title: String::new(),
+            items: lines
+                .iter()
+                .map(|line| FolderItem::ObjString(line.to_string()))
+                .collect(),
+        })
+    }
 
-        Some(BeamlineInfo { name })
+    #[test]
+    fn keep_all_header_entries() {
+        let run_info = RunInfo::parse(&folder(
+            "RunInfo",
+            &[
+                "005 - Main Proposer: Alice -@0",
+                "006 - Main Proposer: Bob -@0",
+                "008 - Run Number: two thousand -@1",
+                "008 - Run Number: 2000 -@1",
+                "030 - Co-Proposer: Carol -@0",
+                "031 - Co-Proposer: Dave -@0",
+                "022 - No of Histos: 8.5 -@2",
+                "Run Title without a type tag",
+            ],
+        ))
+        .unwrap();
+        assert_eq!(run_info.main_proposer, ["Alice", "Bob"]);
+        // Entries of the wrong type are not read, but kept in `extra`
+        assert_eq!(run_info.run_number, 2000);
+        assert_eq!(
+            run_info.extra["Run Number"],
+            [HeaderValue::String("two thousand".into())]
+        );
+        assert_eq!(run_info.no_of_histos, 0);
+        assert_eq!(run_info.extra["No of Histos"], [HeaderValue::Double(8.5)]);
+        assert_eq!(
+            run_info.extra["Run Title without a type tag"],
+            [HeaderValue::String("Run Title without a type tag".into())]
+        );
+        // Repeated labels keep all of their values
+        assert_eq!(
+            run_info.extra["Co-Proposer"],
+            [
+                HeaderValue::String("Carol".into()),
+                HeaderValue::String("Dave".into())
+            ]
+        );
+        assert_eq!(run_info.extra.len(), 4);
     }
 }
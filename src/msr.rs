@@ -0,0 +1,1001 @@
+use std::fmt;
+
+use crate::background::{Background, BackgroundEstimate};
+use crate::error::{AnalysisError, ParsingError};
+use crate::fit::{fit, Estimator, FitData, FitResult, FitRun, FitType, Parameter};
+use crate::grouping::DetectorGroup;
+use crate::lifetime::MUON_LIFETIME;
+use crate::models::MusrRootFile;
+use crate::packing::Packing;
+use crate::theory::{Theory, TheoryFunction};
+
+// musrfit fit description (.msr file). It consists of a title line followed by blocks, e.g.
+//  FITPARAMETER
+//  #      Nr. Name        Value     Step      Pos_Error  Boundaries
+//          1 Alpha        1         0.01      none
+//          2 Asy          0.2       0.01      none       0       0.33
+//  THEORY
+//  asymmetry 2
+//  simplExpo 3          (rate)
+//  RUN data/lem24_his_2000 MUE4 PSI ROOT-NPP
+//  fittype         2         (asymmetry fit)
+//  alpha           1
+//  forward         1
+//  backward        3
+//  fit             0.1       10
+//  packing         100
+//  COMMANDS
+//  MINIMIZE
+//  PLOT 2
+//  runs     1
+//
+// The FITPARAMETER and RUN blocks are parsed, the THEORY, COMMANDS and PLOT blocks are kept as lines and
+// other blocks (e.g. FUNCTIONS or FOURIER) are written back unchanged. Theory functions which cannot be
+// fitted here, e.g. internFld or userFcn, are only reported by `theory` and `fit`, so that every file can be
+// read and written. Parameter numbers in the file start at 1 and are kept as they are in `MsrRun`.
+//
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/user-manual.html#description-of-the-msr-file-format
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsrFile {
+    pub title: String,
+    pub parameters: Vec<Parameter>, // a step of 0 in the file is a fixed parameter
+    pub theory: Vec<String>,
+    pub runs: Vec<MsrRun>,
+    pub commands: Vec<String>,
+    pub plots: Vec<MsrPlot>,
+    pub blocks: Vec<Vec<String>>, // other blocks including their first line, e.g. FUNCTIONS
+    pub statistic: Vec<String>,   // STATISTIC block including its first line
+}
+
+// A RUN block. Detector groups are given by hDecay numbers, i.e. Histo Number plus red / green offset.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MsrRun {
+    pub name: String, // file name without extension, e.g. data/lem24_his_2000
+    pub beamline: String,
+    pub institute: String,
+    pub format: String,        // e.g. ROOT-NPP or MUSR-ROOT
+    pub fit_type: Option<i64>, // 0 single histogram, 2 asymmetry
+    pub alpha: Option<usize>,
+    pub beta: Option<usize>,
+    pub norm: Option<usize>,           // N0 of a single histogram fit
+    pub background_fit: Option<usize>, // background parameter of a single histogram fit
+    pub forward: Vec<i64>,
+    pub backward: Vec<i64>,
+    pub background: Vec<usize>, // first and last bin of the forward and backward background ranges
+    pub background_fix: Vec<f64>, // fixed forward and backward background in counts per bin (backgr.fix)
+    pub map: Vec<usize>,          // parameters of map1, map2, ..., 0 for unused
+    // t0 and first and last good bin of every histogram. The fit takes them from the run header, so they
+    // have to agree with it.
+    pub t0: Vec<f64>,
+    pub data: Vec<i64>,
+    pub fit_range: Option<FitRange>,
+    pub packing: Option<usize>,
+    pub extra: Vec<String>, // other lines, which are written back unchanged
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FitRange {
+    Time(f64, f64),     // in µs, e.g. fit 0.1 10
+    Bins(usize, usize), // offsets from the first and to the last good bin of the forward group, e.g. fit fgb+5 lgb-10
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsrPlot {
+    pub plot_type: i64,
+    pub lines: Vec<String>,
+}
+
+const SEPARATOR: &str = "###############################################################";
+
+impl MsrFile {
+    pub fn parse(content: &str) -> Result<MsrFile, ParsingError> {
+        let mut msr = MsrFile {
+            title: String::new(),
+            parameters: vec![],
+            theory: vec![],
+            runs: vec![],
+            commands: vec![],
+            plots: vec![],
+            blocks: vec![],
+            statistic: vec![],
+        };
+        let mut block = "";
+        for (number, line) in content.lines().enumerate() {
+            let error = |reason: &str| {
+                ParsingError::ParseError(format!("Line {}: {}: '{}'", number + 1, reason, line))
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if msr.title.is_empty() && block.is_empty() {
+                msr.title = trimmed.to_string();
+                block = "TITLE";
+                continue;
+            }
+
+            let mut tokens = trimmed.split_whitespace();
+            let keyword = tokens.next().unwrap_or_default();
+            match keyword {
+                "FITPARAMETER" | "THEORY" | "COMMANDS" => {
+                    block = keyword;
+                    continue;
+                }
+                "RUN" => {
+                    let fields: Vec<&str> = tokens.collect();
+                    if fields.len() < 4 {
+                        return Err(error("Expected RUN <name> <beamline> <institute> <format>"));
+                    }
+                    msr.runs.push(MsrRun {
+                        name: fields[0].to_string(),
+                        beamline: fields[1].to_string(),
+                        institute: fields[2].to_string(),
+                        format: fields[3].to_string(),
+                        ..Default::default()
+                    });
+                    block = keyword;
+                    continue;
+                }
+                "PLOT" => {
+                    let plot_type = tokens
+                        .next()
+                        .and_then(|plot_type| plot_type.parse().ok())
+                        .ok_or_else(|| error("Expected PLOT <type>"))?;
+                    msr.plots.push(MsrPlot {
+                        plot_type,
+                        lines: vec![],
+                    });
+                    block = keyword;
+                    continue;
+                }
+                "STATISTIC" => {
+                    msr.statistic = vec![trimmed.to_string()];
+                    block = keyword;
+                    continue;
+                }
+                "FUNCTIONS" | "GLOBAL" | "FOURIER" => {
+                    msr.blocks.push(vec![trimmed.to_string()]);
+                    block = "OTHER";
+                    continue;
+                }
+                _ => {}
+            }
+
+            match block {
+                "FITPARAMETER" => {
+                    let parameter = parse_parameter(trimmed).map_err(|reason| error(&reason))?;
+                    if parameter.0 != msr.parameters.len() + 1 {
+                        return Err(error("Parameters are not numbered consecutively"));
+                    }
+                    msr.parameters.push(parameter.1);
+                }
+                "THEORY" => msr.theory.push(trimmed.to_string()),
+                "RUN" => {
+                    let run = msr.runs.last_mut().expect("RUN block without run");
+                    run.parse_line(trimmed).map_err(|reason| error(&reason))?;
+                }
+                "COMMANDS" => msr.commands.push(trimmed.to_string()),
+                "PLOT" => {
+                    let plot = msr.plots.last_mut().expect("PLOT block without plot");
+                    plot.lines.push(trimmed.to_string());
+                }
+                "STATISTIC" => msr.statistic.push(trimmed.to_string()),
+                "OTHER" => msr
+                    .blocks
+                    .last_mut()
+                    .expect("Block without first line")
+                    .push(trimmed.to_string()),
+                _ => return Err(error("Line outside of a block")),
+            }
+        }
+        if msr.title.is_empty() {
+            return Err(ParsingError::ParseError("Empty msr file".into()));
+        }
+        Ok(msr)
+    }
+
+    pub fn estimator(&self) -> Estimator {
+        if self
+            .commands
+            .iter()
+            .any(|command| command.eq_ignore_ascii_case("MAX_LIKELIHOOD"))
+        {
+            Estimator::PoissonLikelihood
+        } else {
+            Estimator::ChiSquare
+        }
+    }
+
+    // RUN block `run`, counted from 0
+    fn run(&self, run: usize) -> Result<&MsrRun, AnalysisError> {
+        self.runs
+            .get(run)
+            .ok_or_else(|| AnalysisError::FitFailed(format!("RUN {} is not defined", run + 1)))
+    }
+
+    // Theory of run `run` with parameter indices starting at 0 and mapN resolved through the map of the run
+    pub fn theory(&self, run: usize) -> Result<Theory, AnalysisError> {
+        let map = &self.run(run)?.map;
+        let resolve = |reference: &str| -> Result<usize, String> {
+            let number = match reference.strip_prefix("map") {
+                Some(index) => {
+                    let index: usize = index
+                        .parse()
+                        .map_err(|_| format!("Invalid map '{}'", reference))?;
+                    *index
+                        .checked_sub(1)
+                        .and_then(|index| map.get(index))
+                        .ok_or_else(|| format!("{} is not defined in RUN {}", reference, run + 1))?
+                }
+                None => reference
+                    .parse()
+                    .map_err(|_| format!("Invalid parameter '{}'", reference))?,
+            };
+            parameter_index(number, self.parameters.len())
+        };
+
+        let mut terms = vec![vec![]];
+        for line in &self.theory {
+            if line == "+" {
+                terms.push(vec![]);
+                continue;
+            }
+            let function = theory_function(line, resolve).map_err(AnalysisError::FitFailed)?;
+            terms.last_mut().expect("at least one term").push(function);
+        }
+        // The dynamic Kubo-Toyabe function is only implemented in zero field
+        for line in self.theory.iter().filter(|line| {
+            matches!(
+                line.split_whitespace().next(),
+                Some("dynGssKTLF" | "dgktlf")
+            )
+        }) {
+            let field = line.split_whitespace().nth(1).unwrap_or_default();
+            let field = resolve(field).map_err(AnalysisError::FitFailed)?;
+            let parameter = &self.parameters[field];
+            if !parameter.fixed || parameter.value != 0.0 {
+                return Err(AnalysisError::FitFailed(format!(
+                    "Longitudinal field of '{}' must be fixed to 0",
+                    line
+                )));
+            }
+        }
+        Ok(Theory::new(terms))
+    }
+
+    // Data, theory and fit type of run `run` taken from `musr_root_file`
+    pub fn fit_run(
+        &self,
+        run: usize,
+        musr_root_file: &MusrRootFile,
+    ) -> Result<FitRun, AnalysisError> {
+        let msr_run = self.run(run)?;
+        let failed =
+            |reason: &str| AnalysisError::FitFailed(format!("RUN {}: {}", run + 1, reason));
+        let index = |number: Option<usize>, name: &str| {
+            number
+                .ok_or_else(|| failed(&format!("{} is missing", name)))
+                .and_then(|number| {
+                    parameter_index(number, self.parameters.len()).map_err(|reason| failed(&reason))
+                })
+        };
+        let fit_range = msr_run
+            .fit_range
+            .ok_or_else(|| failed("fit range is missing"))?;
+        let packing = Packing::Fixed(msr_run.packing.unwrap_or(1));
+
+        let (data, fit_type) = match msr_run.fit_type {
+            Some(0) => {
+                if !msr_run.background.is_empty() || !msr_run.background_fix.is_empty() {
+                    return Err(failed("only backgr.fit is supported for single histograms"));
+                }
+                let group = DetectorGroup::new("forward", &msr_run.forward);
+                self.check_header(run, musr_root_file, &[&group])?;
+                let data = FitData::from(
+                    &musr_root_file
+                        .group(&group)?
+                        .pack(&packing, group.background),
+                );
+                let fit_type = FitType::SingleHistogram {
+                    n0: index(msr_run.norm, "norm")?,
+                    background: index(msr_run.background_fit, "backgr.fit")?,
+                    tau: MUON_LIFETIME,
+                };
+                (data, fit_type)
+            }
+            Some(2) => {
+                if let Some(beta) = msr_run.beta {
+                    let beta = &self.parameters[index(Some(beta), "beta")?];
+                    if !beta.fixed || beta.value != 1.0 {
+                        return Err(failed("beta must be fixed to 1"));
+                    }
+                }
+                let mut forward = DetectorGroup::new("forward", &msr_run.forward);
+                let mut backward = DetectorGroup::new("backward", &msr_run.backward);
+                self.check_header(run, musr_root_file, &[&forward, &backward])?;
+                if !msr_run.background.is_empty() && !msr_run.background_fix.is_empty() {
+                    return Err(failed("background and backgr.fix exclude each other"));
+                }
+                if let [forward_fix, backward_fix] = msr_run.background_fix[..] {
+                    forward.background = BackgroundEstimate::fixed(forward_fix);
+                    backward.background = BackgroundEstimate::fixed(backward_fix);
+                } else if !msr_run.background_fix.is_empty() {
+                    return Err(failed("backgr.fix needs two values"));
+                }
+                if let [forward_first, forward_last, backward_first, backward_last] =
+                    msr_run.background[..]
+                {
+                    forward.background = musr_root_file
+                        .group(&forward)?
+                        .background(&Background::Range(forward_first..=forward_last))?;
+                    backward.background = musr_root_file
+                        .group(&backward)?
+                        .background(&Background::Range(backward_first..=backward_last))?;
+                } else if !msr_run.background.is_empty() {
+                    return Err(failed("background needs four bins"));
+                }
+                let asymmetry =
+                    musr_root_file.packed_asymmetry(&forward, &backward, 1.0, None, &packing)?;
+                let fit_type = match msr_run.alpha {
+                    Some(alpha) => FitType::RawAsymmetry {
+                        alpha: index(Some(alpha), "alpha")?,
+                    },
+                    None => FitType::Asymmetry,
+                };
+                (FitData::from(&asymmetry), fit_type)
+            }
+            Some(fit_type) => {
+                return Err(failed(&format!("fittype {} is not supported", fit_type)))
+            }
+            None => return Err(failed("fittype is missing")),
+        };
+        let (start, end) = match fit_range {
+            FitRange::Time(start, end) => (start, end),
+            FitRange::Bins(first, last) => {
+                let forward =
+                    musr_root_file.group(&DetectorGroup::new("forward", &msr_run.forward))?;
+                let good_bins = forward.good_bins();
+                (
+                    forward.time(good_bins.start + first),
+                    forward.time((good_bins.end - 1).saturating_sub(last)),
+                )
+            }
+        };
+        Ok(FitRun::new(
+            data.restrict(start, end),
+            self.theory(run)?,
+            fit_type,
+        ))
+    }
+
+    // t0 and data of run `run`, if given, have to be the ones of the run header of the histograms of `groups`,
+    // since the fit takes them from there
+    fn check_header(
+        &self,
+        run: usize,
+        musr_root_file: &MusrRootFile,
+        groups: &[&DetectorGroup],
+    ) -> Result<(), AnalysisError> {
+        let msr_run = self.run(run)?;
+        let failed =
+            |reason: &str| AnalysisError::FitFailed(format!("RUN {}: {}", run + 1, reason));
+        if !msr_run.t0.is_empty() {
+            let detector_info = &musr_root_file.run_header.detector_info;
+            let header = groups
+                .iter()
+                .flat_map(|group| &group.histograms)
+                .map(|number| {
+                    musr_root_file
+                        .histos
+                        .decay_ana_module
+                        .h_decay
+                        .iter()
+                        .find(|h_decay| h_decay.number == *number)
+                        .and_then(|h_decay| h_decay.detector(detector_info))
+                        .and_then(|detector| detector.time_zero_bin)
+                })
+                .collect::<Vec<_>>();
+            let agrees = header.len() == msr_run.t0.len()
+                && header
+                    .iter()
+                    .zip(&msr_run.t0)
+                    .all(|(header, t0)| header.is_some_and(|header| (header - t0).abs() < 1e-6));
+            if !agrees {
+                return Err(failed("t0 differing from the run header is not supported"));
+            }
+        }
+        if !msr_run.data.is_empty() {
+            let mut header = vec![];
+            for group in groups {
+                let grouped = musr_root_file.group(group)?;
+                header.extend([grouped.first_good_bin, grouped.last_good_bin]);
+            }
+            if header != msr_run.data {
+                return Err(failed(
+                    "data differing from the good bins of the run header is not supported",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Fit all runs, `musr_root_files` in the order of the RUN blocks, and write the fitted values and their
+    // errors into the FITPARAMETER block as musrfit does
+    pub fn fit(&mut self, musr_root_files: &[MusrRootFile]) -> Result<FitResult, AnalysisError> {
+        if musr_root_files.len() != self.runs.len() {
+            return Err(AnalysisError::FitFailed(format!(
+                "{} runs but {} files",
+                self.runs.len(),
+                musr_root_files.len()
+            )));
+        }
+        let runs = musr_root_files
+            .iter()
+            .enumerate()
+            .map(|(run, musr_root_file)| self.fit_run(run, musr_root_file))
+            .collect::<Result<Vec<FitRun>, AnalysisError>>()?;
+        let estimator = self.estimator();
+        let result = fit(&self.parameters, &runs, estimator)?;
+
+        for ((parameter, fitted), error) in self
+            .parameters
+            .iter_mut()
+            .zip(&result.parameters)
+            .zip(&result.errors)
+        {
+            parameter.value = fitted.value;
+            if !parameter.fixed {
+                parameter.step = *error;
+            }
+        }
+        let name = match estimator {
+            Estimator::ChiSquare => "chisq",
+            Estimator::PoissonLikelihood => "maxLH",
+        };
+        self.statistic = vec![
+            "STATISTIC".to_string(),
+            format!(
+                "{} = {}, NDF = {}, {}/NDF = {}",
+                name,
+                number(result.objective),
+                result.ndf,
+                name,
+                number(result.objective / result.ndf as f64)
+            ),
+        ];
+        if !result.converged {
+            self.statistic
+                .push("*** FIT DID NOT CONVERGE ***".to_string());
+        }
+        Ok(result)
+    }
+}
+
+impl MsrRun {
+    // MusrRoot file of the run
+    pub fn root_file(&self) -> String {
+        format!("{}.root", self.name)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or_default();
+        let values: Vec<&str> = tokens.take_while(|token| !token.starts_with('(')).collect();
+        let single = || -> Result<usize, String> {
+            match values[..] {
+                [value] => value
+                    .parse()
+                    .map_err(|_| format!("Invalid {} '{}'", keyword, value)),
+                _ => Err(format!("Expected one value for {}", keyword)),
+            }
+        };
+        let list = || -> Result<Vec<i64>, String> {
+            values
+                .iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("Invalid {}", keyword))
+        };
+        let unsigned = || -> Result<Vec<usize>, String> {
+            values
+                .iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("Invalid {}, expected numbers >= 0", keyword))
+        };
+        let numbers = || -> Result<Vec<f64>, String> {
+            values
+                .iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("Invalid {}", keyword))
+        };
+        match keyword {
+            "fittype" => self.fit_type = Some(single()? as i64),
+            "alpha" => self.alpha = Some(single()?),
+            "beta" => self.beta = Some(single()?),
+            "norm" => self.norm = Some(single()?),
+            "backgr.fit" => self.background_fit = Some(single()?),
+            "packing" => self.packing = Some(single()?),
+            "forward" => self.forward = list()?,
+            "backward" => self.backward = list()?,
+            "background" => self.background = unsigned()?,
+            "backgr.fix" => self.background_fix = numbers()?,
+            "map" => self.map = unsigned()?,
+            "t0" => self.t0.extend(numbers()?),
+            "data" => self.data = list()?,
+            "fit" => self.fit_range = Some(fit_range(&values)?),
+            _ => self.extra.push(line.to_string()),
+        }
+        Ok(())
+    }
+}
+
+// Write the file in the layout of musrfit
+impl fmt::Display for MsrFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        writeln!(f, "{}", SEPARATOR)?;
+        writeln!(f, "FITPARAMETER")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "#      Nr. Name        Value     Step      Pos_Error  Boundaries"
+        )?;
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            let step = if parameter.fixed {
+                "0".to_string()
+            } else {
+                number(parameter.step)
+            };
+            let mut line = format!(
+                "{:>9} {:<12} {:<9} {:<9} none",
+                index + 1,
+                parameter.name,
+                number(parameter.value),
+                step
+            );
+            if parameter.lower.is_some() || parameter.upper.is_some() {
+                let bound = |bound: Option<f64>| bound.map_or("none".to_string(), number);
+                line += &format!(
+                    "       {:<7} {}",
+                    bound(parameter.lower),
+                    bound(parameter.upper)
+                );
+            }
+            writeln!(f, "{}", line)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", SEPARATOR)?;
+        writeln!(f, "THEORY")?;
+        for line in &self.theory {
+            writeln!(f, "{}", line)?;
+        }
+        writeln!(f)?;
+        for block in self
+            .blocks
+            .iter()
+            .filter(|block| !block[0].starts_with("FOURIER"))
+        {
+            write_block(f, block)?;
+        }
+        for run in &self.runs {
+            writeln!(f, "{}", SEPARATOR)?;
+            write!(f, "{}", run)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", SEPARATOR)?;
+        writeln!(f, "COMMANDS")?;
+        for command in &self.commands {
+            writeln!(f, "{}", command)?;
+        }
+        writeln!(f)?;
+        for block in self
+            .blocks
+            .iter()
+            .filter(|block| block[0].starts_with("FOURIER"))
+        {
+            write_block(f, block)?;
+        }
+        for plot in &self.plots {
+            writeln!(f, "{}", SEPARATOR)?;
+            writeln!(f, "PLOT {}", plot.plot_type)?;
+            for line in &plot.lines {
+                writeln!(f, "{}", line)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.statistic.is_empty() {
+            writeln!(f, "{}", SEPARATOR)?;
+            for line in &self.statistic {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MsrRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[i64]| {
+            values
+                .iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        writeln!(
+            f,
+            "RUN {} {} {} {}",
+            self.name, self.beamline, self.institute, self.format
+        )?;
+        if let Some(fit_type) = self.fit_type {
+            let description = match fit_type {
+                0 => "         (single histogram fit)",
+                2 => "         (asymmetry fit)",
+                _ => "",
+            };
+            writeln!(f, "{:<16}{}{}", "fittype", fit_type, description)?;
+        }
+        for (keyword, value) in [
+            ("alpha", self.alpha),
+            ("beta", self.beta),
+            ("norm", self.norm),
+            ("backgr.fit", self.background_fit),
+        ] {
+            if let Some(value) = value {
+                writeln!(f, "{:<16}{}", keyword, value)?;
+            }
+        }
+        if !self.map.is_empty() {
+            let map: Vec<i64> = self.map.iter().map(|number| *number as i64).collect();
+            writeln!(f, "{:<16}{}", "map", join(&map))?;
+        }
+        for (keyword, values) in [("forward", &self.forward), ("backward", &self.backward)] {
+            if !values.is_empty() {
+                writeln!(f, "{:<16}{}", keyword, join(values))?;
+            }
+        }
+        if !self.background.is_empty() {
+            let bins: Vec<i64> = self.background.iter().map(|bin| *bin as i64).collect();
+            writeln!(f, "{:<16}{}", "background", join(&bins))?;
+        }
+        let numbers = |values: &[f64]| {
+            values
+                .iter()
+                .map(|value| number(*value))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        if !self.background_fix.is_empty() {
+            writeln!(f, "{:<16}{}", "backgr.fix", numbers(&self.background_fix))?;
+        }
+        if !self.data.is_empty() {
+            writeln!(f, "{:<16}{}", "data", join(&self.data))?;
+        }
+        if !self.t0.is_empty() {
+            writeln!(f, "{:<16}{}", "t0", numbers(&self.t0))?;
+        }
+        for line in &self.extra {
+            writeln!(f, "{}", line)?;
+        }
+        match self.fit_range {
+            Some(FitRange::Time(start, end)) => {
+                writeln!(f, "{:<16}{:<10}{}", "fit", number(start), number(end))?
+            }
+            Some(FitRange::Bins(first, last)) => {
+                writeln!(f, "{:<16}fgb+{:<6}lgb-{}", "fit", first, last)?
+            }
+            None => {}
+        }
+        if let Some(packing) = self.packing {
+            writeln!(f, "{:<16}{}", "packing", packing)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_block(f: &mut fmt::Formatter<'_>, block: &[String]) -> fmt::Result {
+    writeln!(f, "{}", SEPARATOR)?;
+    for line in block {
+        writeln!(f, "{}", line)?;
+    }
+    writeln!(f)
+}
+
+// Parameter line `<Nr> <Name> <Value> <Step> [<Pos_Error> [<lower> <upper>]]`
+fn parse_parameter(line: &str) -> Result<(usize, Parameter), String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return Err("Expected <Nr> <Name> <Value> <Step>".into());
+    }
+    let value = |token: &str| -> Result<f64, String> {
+        token
+            .parse()
+            .map_err(|_| format!("Invalid number '{}'", token))
+    };
+    let bound = |token: &str| match token {
+        "none" => Ok(None),
+        token => value(token).map(Some),
+    };
+    let number = tokens[0]
+        .parse()
+        .map_err(|_| format!("Invalid parameter number '{}'", tokens[0]))?;
+    let step = value(tokens[3])?;
+    let mut parameter = Parameter::new(tokens[1], value(tokens[2])?);
+    if step == 0.0 {
+        parameter.fixed = true;
+    } else {
+        parameter.step = step.abs();
+    }
+    match tokens[4..] {
+        [] | [_] => {}
+        [_, lower, upper] => {
+            parameter.lower = bound(lower)?;
+            parameter.upper = bound(upper)?;
+        }
+        _ => return Err("Expected lower and upper boundary".into()),
+    }
+    Ok((number, parameter))
+}
+
+// Fit range `<start> <end>` in µs or `fgb+<n> lgb-<m>` in bins relative to the good bins
+fn fit_range(values: &[&str]) -> Result<FitRange, String> {
+    let error = || "Expected fit <start> <end> in µs or fit fgb+<n> lgb-<m>".to_string();
+    let [start, end] = values[..] else {
+        return Err(error());
+    };
+    if let (Ok(start), Ok(end)) = (start.parse(), end.parse()) {
+        return Ok(FitRange::Time(start, end));
+    }
+    let offset = |value: &str, prefix: &str, sign: char| match value.strip_prefix(prefix) {
+        Some("") => Ok(0),
+        Some(offset) => offset
+            .strip_prefix(sign)
+            .and_then(|offset| offset.parse().ok())
+            .ok_or_else(error),
+        None => Err(error()),
+    };
+    Ok(FitRange::Bins(
+        offset(start, "fgb", '+')?,
+        offset(end, "lgb", '-')?,
+    ))
+}
+
+// Theory line `<function> <parameter>...`, comments in parentheses after the parameters are ignored
+fn theory_function(
+    line: &str,
+    resolve: impl Fn(&str) -> Result<usize, String>,
+) -> Result<TheoryFunction, String> {
+    let mut tokens = line.split_whitespace();
+    let name = tokens.next().unwrap_or_default();
+    let arguments: Vec<&str> = tokens.take_while(|token| !token.starts_with('(')).collect();
+    let expected = match name {
+        "asymmetry" | "a" | "simplExpo" | "se" | "simpleGss" | "sg" | "statGssKT" | "stg" => 1,
+        "generExpo" | "ge" | "TFieldCos" | "tf" | "bessel" | "b" => 2,
+        "dynGssKTLF" | "dgktlf" => 3,
+        _ => return Err(format!("Theory function '{}' is not supported", name)),
+    };
+    if arguments.len() != expected {
+        return Err(format!("{} expects {} parameters", name, expected));
+    }
+    let p = arguments
+        .iter()
+        .map(|argument| resolve(argument))
+        .collect::<Result<Vec<usize>, String>>()?;
+    Ok(match name {
+        "asymmetry" | "a" => TheoryFunction::Asymmetry(p[0]),
+        "simplExpo" | "se" => TheoryFunction::SimpleExp(p[0]),
+        "simpleGss" | "sg" => TheoryFunction::SimpleGauss(p[0]),
+        "statGssKT" | "stg" => TheoryFunction::StaticGaussKt(p[0]),
+        "generExpo" | "ge" => TheoryFunction::StretchedExp(p[0], p[1]),
+        "TFieldCos" | "tf" => TheoryFunction::TfCos(p[0], p[1]),
+        "bessel" | "b" => TheoryFunction::Bessel(p[0], p[1]),
+        _ => TheoryFunction::DynamicGaussKt(p[1], p[2]), // the field p[0] is checked to be 0
+    })
+}
+
+// Index of the parameter with number `number` (starting at 1)
+fn parameter_index(number: usize, parameters: usize) -> Result<usize, String> {
+    match number {
+        1.. if number <= parameters => Ok(number - 1),
+        _ => Err(format!("Parameter {} is not defined", number)),
+    }
+}
+
+// Number with 6 significant digits without trailing zeros
+fn number(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let decimals = (5 - value.abs().log10().floor() as i32).clamp(0, 15) as usize;
+    let formatted = format!("{:.*}", decimals, value);
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    const MSR: &str = "\
+lem24_his_2000, T=290 K, B=68 G
+###############################################################
+FITPARAMETER
+
+#      Nr. Name        Value     Step      Pos_Error  Boundaries
+        1 Alpha        1         0.01      none
+        2 Asy          0.05      0.01      none       0       0.33
+        3 Rate         0.1       0.01      none       0       none
+        4 Phase        0         10        none
+        5 Field        0.92      0.01      none
+
+###############################################################
+THEORY
+asymmetry 2
+simplExpo 3          (rate)
+TFieldCos map1 5     (phase frequency)
+
+###############################################################
+RUN lem24_his_2000 MUE4 PSI ROOT-NPP
+fittype         2         (asymmetry fit)
+alpha           1
+map             4
+forward         1
+backward        3
+t0              2834 2834
+fit             0.1       10
+packing         200
+
+###############################################################
+COMMANDS
+MINIMIZE
+HESSE
+SAVE
+
+###############################################################
+PLOT 2
+runs     1
+range    0  10  -0.3  0.3
+";
+
+    #[test]
+    fn parse_and_write() {
+        let msr = MsrFile::parse(MSR).unwrap();
+        assert_eq!(msr.title, "lem24_his_2000, T=290 K, B=68 G");
+        assert_eq!(msr.parameters.len(), 5);
+        assert_eq!(msr.parameters[1].lower, Some(0.0));
+        assert_eq!(msr.parameters[1].upper, Some(0.33));
+        assert_eq!(msr.parameters[2].upper, None);
+        assert!(!msr.parameters[3].fixed);
+        assert_eq!(msr.theory.len(), 3);
+        let run = &msr.runs[0];
+        assert_eq!(run.root_file(), "lem24_his_2000.root");
+        assert_eq!((run.fit_type, run.alpha), (Some(2), Some(1)));
+        assert_eq!(
+            (run.forward.clone(), run.backward.clone()),
+            (vec![1], vec![3])
+        );
+        assert_eq!(run.map, [4]);
+        assert_eq!(run.fit_range, Some(FitRange::Time(0.1, 10.0)));
+        assert_eq!(run.packing, Some(200));
+        assert_eq!(run.t0, [2834.0, 2834.0]);
+        assert!(run.extra.is_empty());
+        assert_eq!(msr.commands, ["MINIMIZE", "HESSE", "SAVE"]);
+        assert_eq!(msr.plots[0].plot_type, 2);
+        assert_eq!(msr.estimator(), Estimator::ChiSquare);
+        assert_eq!(
+            msr.theory(0).unwrap(),
+            Theory::new(vec![vec![
+                TheoryFunction::Asymmetry(1),
+                TheoryFunction::SimpleExp(2),
+                TheoryFunction::TfCos(3, 4),
+            ]])
+        );
+
+        // Writing and parsing again gives the same file
+        assert_eq!(MsrFile::parse(&msr.to_string()).unwrap(), msr);
+
+        // Unsupported theory functions are kept and only reported for the fit
+        let msr = MsrFile::parse(&MSR.replace("simplExpo 3", "userFcn 3")).unwrap();
+        assert!(msr.theory(0).is_err());
+        assert_eq!(MsrFile::parse(&msr.to_string()).unwrap(), msr);
+
+        let msr =
+            MsrFile::parse(&MSR.replace("fit             0.1       10", "fit fgb+5 lgb")).unwrap();
+        assert_eq!(msr.runs[0].fit_range, Some(FitRange::Bins(5, 0)));
+        assert_eq!(MsrFile::parse(&msr.to_string()).unwrap(), msr);
+        assert!(
+            MsrFile::parse(&MSR.replace("fit             0.1       10", "fit fgb-5 lgb")).is_err()
+        );
+        assert!(MsrFile::parse(&MSR.replace("        5 Field", "        6 Field")).is_err());
+        match MsrFile::parse(&MSR.replace("map             4", "map             -1")) {
+            Err(ParsingError::ParseError(message)) => assert!(message.starts_with("Line 22: ")),
+            other => panic!("{:?}", other),
+        }
+        assert!(msr.theory(1).is_err());
+        let msr = MsrFile::parse(&MSR.replace("map             4", "map             0")).unwrap();
+        assert!(msr.theory(0).is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number(1.0), "1");
+        assert_eq!(number(0.123456789), "0.123457");
+        assert_eq!(number(12345.678), "12345.7");
+        assert_eq!(number(-0.000012345678), "-0.0000123457");
+        assert_eq!(number(0.0), "0");
+    }
+
+    #[tokio::test]
+    async fn lem_fit() {
+        // Precession at 0.92 MHz, forward counter 10 % more efficient than backward counter
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        let h_decay = &mut musr_root_file.histos.decay_ana_module.h_decay;
+        for (index, sign, efficiency) in [(0, 1.0, 1.1), (2, -1.0, 1.0)] {
+            let bin_width = h_decay[index].bin_width / 1000.0;
+            for (bin, count) in h_decay[index].contents.iter_mut().enumerate() {
+                let t = (bin as f64 - 2834.0) * bin_width;
+                let asymmetry = 0.2
+                    * (-0.3 * t).exp()
+                    * (2.0 * std::f64::consts::PI * 0.92 * t + 20f64.to_radians()).cos();
+                *count = efficiency * 100.0 * (-t / MUON_LIFETIME).exp() * (1.0 + sign * asymmetry);
+            }
+        }
+
+        let mut msr = MsrFile::parse(MSR).unwrap();
+        let result = msr.fit(std::slice::from_ref(&musr_root_file)).unwrap();
+        assert!(result.converged);
+        // Packing 200 bins averages the precession, which lowers the asymmetry slightly
+        for (parameter, expected) in msr.parameters.iter().zip([1.1, 0.2, 0.3, 20.0, 0.92]) {
+            assert!(
+                (parameter.value - expected).abs() < 5e-3 * expected,
+                "{:?}",
+                msr.parameters
+            );
+            // The step is replaced by the error
+            assert!(parameter.step > 0.0 && parameter.step < 0.1 * expected);
+        }
+
+        // The results are written into the FITPARAMETER block
+        let written = msr.to_string();
+        let parsed = MsrFile::parse(&written).unwrap();
+        assert_eq!(
+            number(parsed.parameters[4].value),
+            number(msr.parameters[4].value)
+        );
+        assert_eq!(parsed.statistic, msr.statistic);
+        assert!(written.contains("chisq = "));
+
+        // t0 and good bins can only be taken from the run header
+        for (from, to) in [
+            ("t0              2834 2834", "t0              2834 2840"),
+            (
+                "t0              2834 2834",
+                "data            2834 66600 2840 66600",
+            ),
+            ("fittype         2", "fittype         4"),
+        ] {
+            let mut msr = MsrFile::parse(&MSR.replace(from, to)).unwrap();
+            assert!(msr.fit(std::slice::from_ref(&musr_root_file)).is_err());
+        }
+        let msr = MsrFile::parse(&MSR.replace(
+            "t0              2834 2834",
+            "data            2834 66600 2834 66600",
+        ))
+        .unwrap();
+        assert!(msr.fit_run(0, &musr_root_file).is_ok());
+        let msr =
+            MsrFile::parse(&MSR.replace("fit             0.1       10", "fit fgb lgb-0")).unwrap();
+        assert!(msr.fit_run(0, &musr_root_file).is_ok());
+    }
+}
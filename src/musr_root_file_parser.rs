@@ -1,17 +1,147 @@
 use crate::error::ParsingError;
 use crate::models::*;
-use std::fs::File;
-use std::io::Read;
+use root_io::core::Folder;
+use root_io::{FileItem, RootFile};
+use std::path::Path;
 
-pub fn parse_musr_root_file(file_path: &str) -> Result<MusrRootFile, ParsingError> {
-    // Open the binary file
-    let mut file = File::open(file_path)?;
+pub async fn parse_musr_root_file(file_path: &str) -> Result<MusrRootFile, ParsingError> {
+    // Open the ROOT file and read its list of keys
+    let file = RootFile::new(Path::new(file_path)).await?;
 
-    // Read the entire contents of the file into a buffer
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+    // The histograms and the run header are stored in two top level TFolders
+    let histos = read_folder(file.items(), "histos").await?;
+    let run_header = read_folder(file.items(), "RunHeader").await?;
 
-    // Parse the buffer into a MusrRootFile struct
-    MusrRootFile::parse(&buffer)
+    // Populate the MusrRootFile struct from the folder contents
+    MusrRootFile::parse(&histos, &run_header)
         .ok_or_else(|| ParsingError::ParseError("Failed to parse MUSR Root File".into()))
 }
+
+pub(crate) async fn read_folder(items: &[FileItem], name: &str) -> Result<Folder, ParsingError> {
+    let item = items
+        .iter()
+        .find(|item| item.obj_name() == name && item.class_name() == "TFolder")
+        .ok_or_else(|| ParsingError::ParseError(format!("Missing `{}` folder", name)))?;
+    Ok(item.as_folder().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header_entry::HeaderValue;
+    use crate::packing::Packing;
+
+    #[tokio::test]
+    async fn parse_lem_run() {
+        let musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+
+        let run_info = &musr_root_file.run_header.run_info;
+        assert_eq!(run_info.run_number, 2000);
+        assert_eq!(run_info.instrument, "LEM");
+        assert_eq!(run_info.no_of_histos, 8);
+        let temperature = run_info.sample_temperature.as_ref().unwrap();
+        assert_eq!((temperature.value, temperature.error), (290.0, Some(0.01)));
+        assert_eq!(temperature.unit, "K");
+        let time_resolution = run_info.time_resolution.as_ref().unwrap();
+        assert_eq!(time_resolution.value, 0.1953125);
+        assert_eq!(time_resolution.unit, "ns");
+        assert_eq!(run_info.proposal_number, Some(20231370));
+        assert_eq!(run_info.main_proposer, ["Khashayar Ghandi"]);
+        assert_eq!(run_info.red_green_offsets, [0, 20, 40, 60]);
+        assert_eq!(
+            run_info.extra["P-Group"][0],
+            HeaderValue::String("p21717".into())
+        );
+        let moderator_hv = run_info.extra["Moderator HV"][0]
+            .as_physical_quantity()
+            .unwrap();
+        assert_eq!(moderator_hv.unit, "kV");
+
+        let run_header = &musr_root_file.run_header;
+        assert!(run_header
+            .beamline_info
+            .extra
+            .contains_key("Beamline Settings"));
+        assert_eq!(
+            run_header.run_summary[1],
+            "Tue Jul 23 12:25:32 2024 Run 2000 stopped."
+        );
+        assert!(run_header.extra.contains_key("ScalerInfo"));
+
+        let h_decay = &musr_root_file.histos.decay_ana_module.h_decay;
+        assert_eq!(h_decay.len(), 32);
+        assert_eq!(h_decay[0].name, "hDecay001");
+        assert_eq!(h_decay[0].number, 1);
+        assert_eq!(h_decay[0].contents.len(), 66601);
+        assert_eq!(h_decay[0].bin_width, 0.1953125);
+        assert!(h_decay[0].contents.iter().sum::<f64>() > 0.0);
+
+        let detectors = &musr_root_file.run_header.detector_info.detectors;
+        assert_eq!(detectors.len(), h_decay.len());
+        assert_eq!(detectors[0].name, "e+ Left D(F), EXT. OFF");
+        assert_eq!(detectors[0].time_zero_bin, Some(2834.0));
+        assert_eq!(detectors[0].last_good_bin, 66600);
+
+        let detector_info = &musr_root_file.run_header.detector_info;
+        let h_decay041 = h_decay.iter().find(|h| h.name == "hDecay041").unwrap();
+        assert_eq!(h_decay041.number, 41);
+        let detector = h_decay041.detector(detector_info).unwrap();
+        assert_eq!(detector.folder_name, "Detector041");
+        assert_eq!(detector.name, "e+ Left D(F), EXT. ON");
+        assert_eq!(detector.histo_number, 1);
+
+        let sc_ana_module = &musr_root_file.histos.sc_ana_module;
+        assert_eq!(sc_ana_module.histos.len(), 8);
+        let temperature = sc_ana_module.get("Sample Temperature").unwrap();
+        assert_eq!(temperature.unit, "K");
+        assert_eq!(temperature.values.len(), 149);
+        assert!(temperature.times[0].abs() < 0.1);
+        assert!((temperature.times[148] - 745.0).abs() < 0.1);
+        let statistics = temperature.statistics().unwrap();
+        assert!((statistics.mean - 290.0).abs() < 0.05);
+        assert!(statistics.min <= statistics.mean && statistics.mean <= statistics.max);
+        assert_eq!(sc_ana_module.get("Moderator HV").unwrap().unit, "kV");
+        assert!(sc_ana_module.get("dummy").unwrap().is_empty());
+        assert!(sc_ana_module.get("Sample Pressure").is_none());
+
+        // At LEM only the electric field is switched, i.e. offsets 0 and 40 are in use
+        let decay_ana_module = &musr_root_file.histos.decay_ana_module;
+        assert_eq!(decay_ana_module.red_green_offsets(), [0, 40]);
+        let field_on: Vec<_> = decay_ana_module
+            .mode(40)
+            .iter()
+            .map(|h| h.name.as_str())
+            .collect();
+        assert_eq!(field_on.len(), 16);
+        assert_eq!(field_on[..2], ["hDecay041", "hDecay042"]);
+        assert_eq!(field_on[8], "hDecay061");
+        assert!(decay_ana_module.mode(20).is_empty());
+        for h in decay_ana_module.mode(40) {
+            assert!(h.detector(detector_info).unwrap().name.ends_with("EXT. ON"));
+        }
+        let detector = h_decay[0].detector(detector_info).unwrap();
+        let good_times = h_decay[0].good_times(detector).unwrap();
+        assert_eq!(h_decay[0].good_counts(detector).len(), 66600 - 2834 + 1);
+        assert_eq!(good_times.len(), 66600 - 2834 + 1);
+        assert_eq!(good_times[0], 0.0);
+        assert!((good_times[good_times.len() - 1] - 12.454296875).abs() < 1e-12);
+        let packed = h_decay[0]
+            .pack(detector, &Packing::Fixed(50), Default::default())
+            .unwrap();
+        assert_eq!(packed.counts.len(), good_times.len() / 50);
+        assert_eq!(
+            packed.counts.iter().sum::<f64>(),
+            h_decay[0].good_counts(detector)[..packed.counts.len() * 50]
+                .iter()
+                .sum::<f64>()
+        );
+        for h in h_decay {
+            assert_eq!(
+                h.detector(detector_info).unwrap().histo_length as usize,
+                h.contents.len()
+            );
+        }
+    }
+}
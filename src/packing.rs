@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::background::BackgroundEstimate;
+use crate::error::AnalysisError;
+use crate::models::{Detector, HDecay};
+
+// Packing combines adjacent bins of the good-bin window, starting at First Good Bin. Times stay relative to
+// t0, so packed histograms of detectors with different t0 still line up. A last packed bin which would
+// be incomplete is dropped, as in musrfit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packing {
+    Fixed(usize), // number of bins combined, 1 for no packing
+    // Packing from a time in µs on, e.g. [(0.0, 10), (5.0, 50)] for late times with low statistics.
+    // Bins before the first time are not packed.
+    Variable(Vec<(f64, usize)>),
+    // Packed bins growing by `growth` from `first` bins on
+    Logarithmic { first: usize, growth: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackedHistogram {
+    pub time: Vec<f64>, // center of each packed bin in µs relative to t0
+    pub counts: Vec<f64>,
+    pub errors: Vec<f64>,
+    pub bins: Vec<usize>, // number of combined bins, for normalizing variable packing
+}
+
+impl Packing {
+    // Ranges of combined bins for bins with center `times` (in µs)
+    pub fn ranges(&self, times: &[f64]) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut start = 0;
+        let mut width = match self {
+            Packing::Logarithmic { first, .. } => *first as f64,
+            _ => 1.0,
+        };
+        while start < times.len() {
+            let pack = match self {
+                Packing::Fixed(pack) => *pack,
+                Packing::Variable(steps) => steps
+                    .iter()
+                    .rev()
+                    .find(|(time, _)| *time <= times[start])
+                    .map_or(1, |(_, pack)| *pack),
+                Packing::Logarithmic { growth, .. } => {
+                    let pack = width.round() as usize;
+                    width *= growth;
+                    pack
+                }
+            }
+            .max(1);
+            if start + pack > times.len() {
+                break;
+            }
+            ranges.push(start..start + pack);
+            start += pack;
+        }
+        ranges
+    }
+}
+
+impl HDecay {
+    // Pack the good-bin window of the histogram. The background is subtracted from every bin; its error
+    // is fully correlated between the combined bins.
+    pub fn pack(
+        &self,
+        detector: &Detector,
+        packing: &Packing,
+        background: BackgroundEstimate,
+    ) -> Result<PackedHistogram, AnalysisError> {
+        let counts = self.good_counts(detector);
+        let times = self.good_times(detector)?;
+        Ok(pack(&times, counts, packing, background))
+    }
+}
+
+pub(crate) fn pack(
+    times: &[f64],
+    counts: &[f64],
+    packing: &Packing,
+    background: BackgroundEstimate,
+) -> PackedHistogram {
+    let mut packed = PackedHistogram {
+        time: vec![],
+        counts: vec![],
+        errors: vec![],
+        bins: vec![],
+    };
+    for range in packing.ranges(times) {
+        let (count, variance) = packed_bin(&counts[range.clone()], background);
+        packed
+            .time
+            .push((times[range.start] + times[range.end - 1]) / 2.0);
+        packed.counts.push(count);
+        packed.errors.push(variance.sqrt());
+        packed.bins.push(range.len());
+    }
+    packed
+}
+
+// Background corrected sum of `counts` and its variance. As in musrfit an empty bin is given an error of
+// 1 count.
+pub(crate) fn packed_bin(counts: &[f64], background: BackgroundEstimate) -> (f64, f64) {
+    let n = counts.len() as f64;
+    let sum: f64 = counts.iter().sum();
+    (
+        sum - n * background.value,
+        sum.max(1.0) + (n * background.error).powi(2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing_ranges() {
+        let times: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(Packing::Fixed(1).ranges(&times).len(), 10);
+        assert_eq!(Packing::Fixed(4).ranges(&times), [0..4, 4..8]);
+        assert_eq!(
+            Packing::Variable(vec![(2.0, 2), (5.0, 3)]).ranges(&times),
+            [0..1, 1..2, 2..4, 4..6, 6..9]
+        );
+        assert_eq!(
+            Packing::Logarithmic {
+                first: 1,
+                growth: 2.0
+            }
+            .ranges(&times),
+            [0..1, 1..3, 3..7]
+        );
+        assert!(Packing::Fixed(20).ranges(&times).is_empty());
+    }
+
+    #[test]
+    fn poisson_errors() {
+        let times = [0.0, 0.1, 0.2, 0.3, 0.4];
+        let counts = [10.0, 20.0, 0.0, 0.0, 1.0];
+        let packed = pack(&times, &counts, &Packing::Fixed(2), Default::default());
+        assert_eq!(packed.counts, [30.0, 0.0]);
+        assert_eq!(packed.errors, [30f64.sqrt(), 1.0]);
+        assert_eq!(packed.bins, [2, 2]);
+        assert!((packed.time[0] - 0.05).abs() < 1e-12);
+
+        let background = BackgroundEstimate {
+            value: 2.0,
+            error: 0.5,
+        };
+        let packed = pack(&times, &counts, &Packing::Fixed(2), background);
+        assert_eq!(packed.counts, [26.0, -4.0]);
+        assert_eq!(packed.errors[0], 31f64.sqrt());
+    }
+}
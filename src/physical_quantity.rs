@@ -0,0 +1,142 @@
+use std::fmt;
+
+use serde::Serialize;
+
+// TMusrRunPhysicalQuantity: <value> +- <estimated error> <unit>; SP: <demand>; <description>
+// Everything except value and unit is optional. Instead of a single value a range can be given.
+// Examples:
+//  28.1 MeV/c
+//  3.21 +- 0.05 K; SP: 3.2; CF1
+//  0.1953125 ns; TDC CAEN V1190
+//  3.2 - 3.5 K
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/musr-root.html#musr-run-physical-quantity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhysicalQuantity {
+    pub value: f64,                // for ranges the center of the range
+    pub error: Option<f64>,        // estimated error
+    pub range: Option<(f64, f64)>, // lower and upper limit, e.g. 3.2 - 3.5 K
+    pub demand: Option<f64>,       // set point (SP)
+    pub unit: String,
+    pub description: Option<String>, // e.g. CF1 or TDC CAEN V1190
+}
+
+impl PhysicalQuantity {
+    pub fn parse(representation: &str) -> Option<PhysicalQuantity> {
+        let mut parts = representation.split(';').map(str::trim);
+
+        let mut tokens = parts.next()?.split_whitespace();
+        let value: f64 = tokens.next()?.parse().ok()?;
+        let mut tokens = tokens.peekable();
+        let (value, error, range) = match tokens.peek() {
+            Some(&"+-") => {
+                tokens.next();
+                (value, Some(tokens.next()?.parse().ok()?), None)
+            }
+            Some(&"-") => {
+                tokens.next();
+                let upper: f64 = tokens.next()?.parse().ok()?;
+                ((value + upper) / 2.0, None, Some((value, upper)))
+            }
+            _ => (value, None, None),
+        };
+        let unit = tokens.collect::<Vec<_>>().join(" ");
+
+        let mut demand = None;
+        let mut description = Vec::new();
+        for part in parts.filter(|part| !part.is_empty()) {
+            match part.strip_prefix("SP:") {
+                Some(sp) if demand.is_none() => demand = Some(sp.trim().parse().ok()?),
+                _ => description.push(part),
+            }
+        }
+        let description = if description.is_empty() {
+            None
+        } else {
+            Some(description.join("; "))
+        };
+
+        Some(PhysicalQuantity {
+            value,
+            error,
+            range,
+            demand,
+            unit,
+            description,
+        })
+    }
+}
+
+// Writes the same representation as TMusrRunPhysicalQuantity
+impl fmt::Display for PhysicalQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.range, self.error) {
+            (Some((lower, upper)), _) => write!(f, "{} - {}", lower, upper)?,
+            (None, Some(error)) => write!(f, "{} +- {}", self.value, error)?,
+            (None, None) => write!(f, "{}", self.value)?,
+        }
+        if !self.unit.is_empty() {
+            write!(f, " {}", self.unit)?;
+        }
+        if let Some(demand) = self.demand {
+            write!(f, "; SP: {}", demand)?;
+        }
+        if let Some(description) = &self.description {
+            write!(f, "; {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_representations() {
+        let quantity = PhysicalQuantity::parse("28.1 MeV/c").unwrap();
+        assert_eq!(quantity.value, 28.1);
+        assert_eq!(quantity.unit, "MeV/c");
+        assert_eq!(quantity.error, None);
+
+        let quantity = PhysicalQuantity::parse("3.21 +- 0.05 K; SP: 3.2; CF1").unwrap();
+        assert_eq!(quantity.value, 3.21);
+        assert_eq!(quantity.error, Some(0.05));
+        assert_eq!(quantity.demand, Some(3.2));
+        assert_eq!(quantity.unit, "K");
+        assert_eq!(quantity.description.as_deref(), Some("CF1"));
+
+        let quantity = PhysicalQuantity::parse("-2.80114 +- 0.00003 kV").unwrap();
+        assert_eq!(quantity.value, -2.80114);
+        assert_eq!(quantity.error, Some(0.00003));
+
+        let quantity = PhysicalQuantity::parse("0.1953125 ns; TDC CAEN V1190").unwrap();
+        assert_eq!(quantity.value, 0.1953125);
+        assert_eq!(quantity.unit, "ns");
+        assert_eq!(quantity.demand, None);
+        assert_eq!(quantity.description.as_deref(), Some("TDC CAEN V1190"));
+
+        let quantity = PhysicalQuantity::parse("350 G; SP: 350").unwrap();
+        assert_eq!(quantity.demand, Some(350.0));
+        assert_eq!(quantity.description, None);
+
+        let quantity = PhysicalQuantity::parse("3.2 - 3.5 K").unwrap();
+        assert_eq!(quantity.range, Some((3.2, 3.5)));
+        assert!((quantity.value - 3.35).abs() < 1e-12);
+
+        assert!(PhysicalQuantity::parse("n/a").is_none());
+        assert!(PhysicalQuantity::parse("3.2 +- K").is_none());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for representation in [
+            "3.21 +- 0.05 K; SP: 3.2; CF1",
+            "0.1953125 ns; TDC CAEN V1190",
+            "3.2 - 3.5 K",
+            "739 sec",
+        ] {
+            let quantity = PhysicalQuantity::parse(representation).unwrap();
+            assert_eq!(quantity.to_string(), representation);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use root_io::hist_reader::Histogram1D;
+use serde::Serialize;
+
+// A slow control histogram of histos/SCAnaModule (`SlowControlHistoEntry` in MusrRoot.xsd), e.g. the
+// sample temperature, recorded as one bin per reading over the run time:
+//  x axis: time (sec since SOR), i.e. since the start of the run
+//  bin content: value of the reading
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlowControlHisto {
+    pub name: String,       // e.g. Sample Temperature
+    pub class_name: String, // ROOT class, e.g. TH1F
+    pub unit: String,       // e.g. K; empty if unknown
+    pub times: Vec<f64>,    // in sec since the start of the run
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Statistics {
+    pub mean: f64,
+    pub std: f64, // sample standard deviation
+    pub min: f64,
+    pub max: f64,
+    pub drift: f64, // change over the run according to a straight line fit
+}
+
+impl SlowControlHisto {
+    // The unit is taken from a y axis title like `T (K)` or `T [K]`, otherwise `unit` is used
+    pub fn from_histogram(histogram: &Histogram1D, unit: &str) -> SlowControlHisto {
+        // An empty histogram (e.g. `dummy`) contains no readings at all
+        let readings = if histogram.entries > 0.0 {
+            histogram.nbins()
+        } else {
+            0
+        };
+        let times = (1..=readings)
+            .map(|bin| histogram.x_axis.bin_center(bin))
+            .collect();
+        let values = histogram.bins()[..readings].to_vec();
+        let unit = axis_unit(&histogram.y_axis.title).unwrap_or(unit);
+
+        SlowControlHisto {
+            name: histogram.name.clone(),
+            class_name: histogram.class_name.clone(),
+            unit: unit.to_string(),
+            times,
+            values,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.values.iter().sum::<f64>() / self.values.len() as f64)
+    }
+
+    pub fn std(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let n = self.values.len();
+        if n < 2 {
+            return Some(0.0);
+        }
+        let sum_of_squares: f64 = self.values.iter().map(|v| (v - mean).powi(2)).sum();
+        Some((sum_of_squares / (n - 1) as f64).sqrt())
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::max)
+    }
+
+    // Slope of a least squares straight line times the time span of the readings. Unlike the
+    // difference between the last and the first reading this is not dominated by noise.
+    pub fn drift(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let n = self.times.len() as f64;
+        let mean_time = self.times.iter().sum::<f64>() / n;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (t, v) in self.times.iter().zip(&self.values) {
+            sxy += (t - mean_time) * (v - mean);
+            sxx += (t - mean_time).powi(2);
+        }
+        if sxx == 0.0 {
+            return Some(0.0);
+        }
+        let span = self.times[self.times.len() - 1] - self.times[0];
+        Some(sxy / sxx * span)
+    }
+
+    pub fn statistics(&self) -> Option<Statistics> {
+        Some(Statistics {
+            mean: self.mean()?,
+            std: self.std()?,
+            min: self.min()?,
+            max: self.max()?,
+            drift: self.drift()?,
+        })
+    }
+}
+
+// `T (K)` or `T [K]` -> `K`
+fn axis_unit(title: &str) -> Option<&str> {
+    let title = title.trim_end();
+    let open = match title.chars().last()? {
+        ')' => '(',
+        ']' => '[',
+        _ => return None,
+    };
+    let start = title.rfind(open)?;
+    let unit = title[start + 1..title.len() - 1].trim();
+    if unit.is_empty() {
+        None
+    } else {
+        Some(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(times: &[f64], values: &[f64]) -> SlowControlHisto {
+        SlowControlHisto {
+            name: "Sample Temperature".into(),
+            class_name: "TH1F".into(),
+            unit: "K".into(),
+            times: times.to_vec(),
+            values: values.to_vec(),
+        }
+    }
+
+    #[test]
+    fn statistics() {
+        let histo = series(&[0.0, 5.0, 10.0, 15.0], &[10.0, 10.5, 11.5, 12.0]);
+        let statistics = histo.statistics().unwrap();
+        assert_eq!(statistics.mean, 11.0);
+        assert!((statistics.std - (5.0f64 / 6.0).sqrt()).abs() < 1e-12);
+        assert_eq!((statistics.min, statistics.max), (10.0, 12.0));
+        // Fitted slope 0.14 per second over 15 seconds
+        assert!((statistics.drift - 2.1).abs() < 1e-12);
+
+        let constant = series(&[0.0], &[3.0]).statistics().unwrap();
+        assert_eq!((constant.std, constant.drift), (0.0, 0.0));
+        assert_eq!(series(&[], &[]).statistics(), None);
+    }
+
+    #[test]
+    fn axis_units() {
+        assert_eq!(axis_unit("T (K)"), Some("K"));
+        assert_eq!(axis_unit("B [G] "), Some("G"));
+        assert_eq!(axis_unit("time (sec since SOR)"), Some("sec since SOR"));
+        assert_eq!(axis_unit("Sample Vacuum"), None);
+        assert_eq!(axis_unit("()"), None);
+        assert_eq!(axis_unit(""), None);
+    }
+}
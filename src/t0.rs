@@ -0,0 +1,236 @@
+use serde::Serialize;
+
+use crate::models::{HDecay, MusrRootFile};
+
+// Minimal height of the prompt peak above the baseline in units of the baseline's Poisson error
+const PEAK_SIGNIFICANCE: f64 = 5.0;
+
+// Prompt peak of a decay histogram, i.e. positrons and electrons arriving together with the muons
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PromptPeak {
+    pub maximum_bin: usize, // bin with the most counts
+    pub centroid: f64,      // center of a Gaussian fitted to the peak, in bins
+    pub centroid_error: f64,
+    pub sigma: f64, // width of the Gaussian, in bins
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum T0Status {
+    Agrees,
+    Disagrees, // the prompt peak is further than the tolerance from Time Zero Bin
+    NoPeak,    // no significant prompt peak, e.g. at LEM where t0 is not given by a prompt peak
+    Missing, // the histogram has no detector or the detector no Time Zero Bin; `peak` may suggest one
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct T0Check {
+    pub histogram: String,
+    pub header: Option<f64>, // Time Zero Bin of the detector
+    pub peak: Option<PromptPeak>,
+    pub status: T0Status,
+}
+
+impl HDecay {
+    // Find the prompt peak: the maximum and the centroid of a Gaussian fitted to the bins above half of
+    // the maximum. The Gaussian is fitted as a parabola to the logarithm of the counts (Caruana's
+    // algorithm), which gives the centroid with sub-bin precision.
+    pub fn find_prompt_peak(&self) -> Option<PromptPeak> {
+        let (maximum_bin, maximum) = self
+            .contents
+            .iter()
+            .copied()
+            .enumerate()
+            .reduce(|max, bin| if bin.1 > max.1 { bin } else { max })?;
+        let baseline = median(&self.contents);
+        let height = maximum - baseline;
+        if height <= PEAK_SIGNIFICANCE * baseline.max(1.0).sqrt() {
+            return None;
+        }
+
+        // Bins above half maximum around the maximum, at least its neighbours
+        let above = |bin: &usize| self.contents[*bin] - baseline >= height / 2.0;
+        let first = (0..maximum_bin)
+            .rev()
+            .take_while(above)
+            .last()
+            .unwrap_or(maximum_bin)
+            .min(maximum_bin.saturating_sub(1));
+        let last = (maximum_bin + 1..self.contents.len())
+            .take_while(above)
+            .last()
+            .unwrap_or(maximum_bin)
+            .max(maximum_bin + 1)
+            .min(self.contents.len() - 1);
+
+        // Weighted least squares fit of ln(N) = a + b x + c x^2 with x relative to the maximum, weights N
+        let mut sums = [0.0; 5]; // sum of w x^k
+        let mut rhs = [0.0; 3]; // sum of w x^k ln(N)
+        let mut counts = 0.0;
+        for bin in first..=last {
+            let n = self.contents[bin] - baseline;
+            if n <= 0.0 {
+                continue;
+            }
+            let x = bin as f64 - maximum_bin as f64;
+            for (k, sum) in sums.iter_mut().enumerate() {
+                *sum += n * x.powi(k as i32);
+            }
+            for (k, sum) in rhs.iter_mut().enumerate() {
+                *sum += n * x.powi(k as i32) * n.ln();
+            }
+            counts += n;
+        }
+        let matrix = [
+            [sums[0], sums[1], sums[2]],
+            [sums[1], sums[2], sums[3]],
+            [sums[2], sums[3], sums[4]],
+        ];
+        let [_, b, c] = solve3(matrix, rhs)?;
+        if c >= 0.0 {
+            return None;
+        }
+        let sigma = (-1.0 / (2.0 * c)).sqrt();
+        let offset = -b / (2.0 * c);
+        // A peak with its center outside of the fitted bins is no peak
+        if offset < first as f64 - maximum_bin as f64 || offset > last as f64 - maximum_bin as f64 {
+            return None;
+        }
+
+        Some(PromptPeak {
+            maximum_bin,
+            centroid: maximum_bin as f64 + offset,
+            centroid_error: sigma / counts.sqrt(),
+            sigma,
+        })
+    }
+}
+
+impl MusrRootFile {
+    // Compare the prompt peak of every decay histogram with the Time Zero Bin of its detector,
+    // `tolerance` in bins
+    pub fn check_t0(&self, tolerance: f64) -> Vec<T0Check> {
+        let detector_info = &self.run_header.detector_info;
+        self.histos
+            .decay_ana_module
+            .h_decay
+            .iter()
+            .map(|h_decay| {
+                let header = h_decay
+                    .detector(detector_info)
+                    .and_then(|detector| detector.time_zero_bin);
+                let peak = h_decay.find_prompt_peak();
+                let status = match (header, peak) {
+                    (None, _) => T0Status::Missing,
+                    (_, None) => T0Status::NoPeak,
+                    (Some(header), Some(peak)) if (peak.centroid - header).abs() > tolerance => {
+                        T0Status::Disagrees
+                    }
+                    _ => T0Status::Agrees,
+                };
+                T0Check {
+                    histogram: h_decay.name.clone(),
+                    header,
+                    peak,
+                    status,
+                }
+            })
+            .collect()
+    }
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => sorted[n / 2],
+        n => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+    }
+}
+
+// Solve a 3x3 linear system with Cramer's rule
+fn solve3(m: [[f64; 3]; 3], rhs: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let determinant = det(m);
+    if determinant.abs() < f64::EPSILON {
+        return None;
+    }
+    let mut solution = [0.0; 3];
+    for (column, value) in solution.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][column] = rhs[row];
+        }
+        *value = det(replaced) / determinant;
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_fixtures::h_decay;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    #[test]
+    fn prompt_peak_centroid() {
+        // Gaussian prompt peak at bin 100.3 with sigma 2 on a background of 20
+        let contents = (0..500)
+            .map(|bin| {
+                let x = (bin as f64 - 100.3) / 2.0;
+                20.0 + 5000.0 * (-x * x / 2.0).exp()
+            })
+            .collect();
+        let peak = h_decay(contents).find_prompt_peak().unwrap();
+        assert_eq!(peak.maximum_bin, 100);
+        assert!((peak.centroid - 100.3).abs() < 1e-9);
+        assert!((peak.sigma - 2.0).abs() < 1e-9);
+        assert!(peak.centroid_error < 0.1);
+
+        // Flat histogram with noise only
+        let contents = (0..500).map(|bin| 20.0 + (bin % 3) as f64).collect();
+        assert_eq!(h_decay(contents).find_prompt_peak(), None);
+        assert_eq!(h_decay(vec![]).find_prompt_peak(), None);
+    }
+
+    #[tokio::test]
+    async fn lem_prompt_peaks() {
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        let checks = musr_root_file.check_t0(1.0);
+        assert_eq!(checks.len(), 32);
+        // Without electric field there is no prompt peak
+        assert!(checks[..16]
+            .iter()
+            .all(|check| check.status == T0Status::NoPeak));
+        assert_eq!(checks[0].header, Some(2834.0));
+
+        // A prompt peak 3 bins after Time Zero Bin
+        let contents = &mut musr_root_file.histos.decay_ana_module.h_decay[0].contents;
+        contents[2836] += 400.0;
+        contents[2837] += 1000.0;
+        contents[2838] += 400.0;
+        let checks = musr_root_file.check_t0(1.0);
+        assert_eq!(checks[0].status, T0Status::Disagrees);
+        let peak = checks[0].peak.unwrap();
+        assert_eq!(peak.maximum_bin, 2837);
+        assert!((peak.centroid - 2837.0).abs() < 0.01);
+        assert_eq!(musr_root_file.check_t0(5.0)[0].status, T0Status::Agrees);
+
+        // Without Time Zero Bin or without a detector the peak is still reported
+        musr_root_file.run_header.detector_info.detectors[0].time_zero_bin = None;
+        musr_root_file.histos.decay_ana_module.h_decay[1].detector = None;
+        let checks = musr_root_file.check_t0(1.0);
+        assert_eq!(checks.len(), 32);
+        assert_eq!(checks[0].status, T0Status::Missing);
+        assert_eq!(checks[0].header, None);
+        assert_eq!(checks[0].peak.unwrap().maximum_bin, 2837);
+        assert_eq!(checks[1].status, T0Status::Missing);
+    }
+}
@@ -0,0 +1,306 @@
+use std::f64::consts::{FRAC_2_PI, PI};
+
+// Theory functions of a fit as in the THEORY block of musrfit. A theory is a sum of products of
+// functions, e.g.
+//  asymmetry 1
+//  simplExpo 2
+//  TFieldCos 3 4
+//  +
+//  asymmetry 5
+//  simplExpo 6
+// becomes `Theory { terms: vec![vec![Asymmetry(0), SimpleExp(1), TfCos(2, 3)], vec![Asymmetry(4), SimpleExp(5)]] }`.
+// Arguments are indices into the parameter list of the fit; rates and fields are in µs^-1 and MHz, phases in
+// degrees.
+//
+// Check link for documentation: https://lmu.web.psi.ch/musrfit/user/html/user-manual.html#the-theory-block
+#[derive(Debug, Clone, PartialEq)]
+pub enum TheoryFunction {
+    Asymmetry(usize),             // A
+    SimpleExp(usize),             // exp(-lambda t)
+    SimpleGauss(usize),           // exp(-(sigma t)^2 / 2)
+    StretchedExp(usize, usize),   // exp(-(lambda t)^beta)
+    TfCos(usize, usize),          // cos(2 pi nu t + phi)
+    StaticGaussKt(usize),         // 1/3 + 2/3 (1 - (Delta t)^2) exp(-(Delta t)^2 / 2)
+    DynamicGaussKt(usize, usize), // strong collision Gaussian Kubo-Toyabe with Delta and hopping rate nu
+    Bessel(usize, usize),         // J0(2 pi nu t + phi)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theory {
+    pub terms: Vec<Vec<TheoryFunction>>, // sum of products
+}
+
+impl TheoryFunction {
+    // Indices of the parameters the function depends on
+    pub fn parameters(&self) -> Vec<usize> {
+        match *self {
+            TheoryFunction::Asymmetry(p)
+            | TheoryFunction::SimpleExp(p)
+            | TheoryFunction::SimpleGauss(p)
+            | TheoryFunction::StaticGaussKt(p) => vec![p],
+            TheoryFunction::StretchedExp(p, q)
+            | TheoryFunction::TfCos(p, q)
+            | TheoryFunction::DynamicGaussKt(p, q)
+            | TheoryFunction::Bessel(p, q) => vec![p, q],
+        }
+    }
+
+    // The same function with parameter `i` replaced by `map[i]`
+    pub fn remap(&self, map: &[usize]) -> TheoryFunction {
+        match *self {
+            TheoryFunction::Asymmetry(p) => TheoryFunction::Asymmetry(map[p]),
+            TheoryFunction::SimpleExp(p) => TheoryFunction::SimpleExp(map[p]),
+            TheoryFunction::SimpleGauss(p) => TheoryFunction::SimpleGauss(map[p]),
+            TheoryFunction::StaticGaussKt(p) => TheoryFunction::StaticGaussKt(map[p]),
+            TheoryFunction::StretchedExp(p, q) => TheoryFunction::StretchedExp(map[p], map[q]),
+            TheoryFunction::TfCos(p, q) => TheoryFunction::TfCos(map[p], map[q]),
+            TheoryFunction::DynamicGaussKt(p, q) => TheoryFunction::DynamicGaussKt(map[p], map[q]),
+            TheoryFunction::Bessel(p, q) => TheoryFunction::Bessel(map[p], map[q]),
+        }
+    }
+
+    pub fn evaluate(&self, times: &[f64], parameters: &[f64]) -> Vec<f64> {
+        let p = |index: usize| parameters[index];
+        match *self {
+            TheoryFunction::Asymmetry(a) => vec![p(a); times.len()],
+            TheoryFunction::SimpleExp(lambda) => {
+                times.iter().map(|t| (-p(lambda) * t).exp()).collect()
+            }
+            TheoryFunction::SimpleGauss(sigma) => times
+                .iter()
+                .map(|t| (-0.5 * (p(sigma) * t).powi(2)).exp())
+                .collect(),
+            TheoryFunction::StretchedExp(lambda, beta) => times
+                .iter()
+                .map(|t| (-(p(lambda) * t).abs().powf(p(beta))).exp())
+                .collect(),
+            TheoryFunction::TfCos(phase, frequency) => times
+                .iter()
+                .map(|t| (2.0 * PI * p(frequency) * t + p(phase).to_radians()).cos())
+                .collect(),
+            TheoryFunction::StaticGaussKt(delta) => times
+                .iter()
+                .map(|t| static_gauss_kt(p(delta), *t))
+                .collect(),
+            TheoryFunction::DynamicGaussKt(delta, hopping) => {
+                dynamic_gauss_kt(p(delta), p(hopping), times)
+            }
+            TheoryFunction::Bessel(phase, frequency) => times
+                .iter()
+                .map(|t| bessel_j0(2.0 * PI * p(frequency) * t + p(phase).to_radians()))
+                .collect(),
+        }
+    }
+}
+
+impl Theory {
+    pub fn new(terms: Vec<Vec<TheoryFunction>>) -> Theory {
+        Theory { terms }
+    }
+
+    // Indices of all parameters the theory depends on
+    pub fn parameters(&self) -> Vec<usize> {
+        let mut parameters: Vec<usize> = self
+            .terms
+            .iter()
+            .flatten()
+            .flat_map(TheoryFunction::parameters)
+            .collect();
+        parameters.sort_unstable();
+        parameters.dedup();
+        parameters
+    }
+
+    // The same theory with parameter `i` replaced by `map[i]`, e.g. to map the parameters of one run of a
+    // global fit
+    pub fn remap(&self, map: &[usize]) -> Theory {
+        Theory::new(
+            self.terms
+                .iter()
+                .map(|term| term.iter().map(|function| function.remap(map)).collect())
+                .collect(),
+        )
+    }
+
+    pub fn evaluate(&self, times: &[f64], parameters: &[f64]) -> Vec<f64> {
+        let mut sum = vec![0.0; times.len()];
+        for term in &self.terms {
+            let mut product = vec![1.0; times.len()];
+            for function in term {
+                for (value, factor) in product.iter_mut().zip(function.evaluate(times, parameters))
+                {
+                    *value *= factor;
+                }
+            }
+            for (value, term) in sum.iter_mut().zip(product) {
+                *value += term;
+            }
+        }
+        sum
+    }
+}
+
+fn static_gauss_kt(delta: f64, t: f64) -> f64 {
+    let x = (delta * t).powi(2);
+    1.0 / 3.0 + 2.0 / 3.0 * (1.0 - x) * (-0.5 * x).exp()
+}
+
+// Strong collision dynamic Kubo-Toyabe function as solution of the Volterra equation
+//  G(t) = g(t) exp(-nu t) + nu int_0^t g(s) exp(-nu s) G(t - s) ds
+// with the static function g. The equation is solved on a grid where g(s) G(t - s) is interpolated linearly
+// and integrated exactly against exp(-nu s), so that fast hopping does not require a fine grid.
+fn dynamic_gauss_kt(delta: f64, hopping: f64, times: &[f64]) -> Vec<f64> {
+    if hopping <= 0.0 {
+        return times.iter().map(|t| static_gauss_kt(delta, *t)).collect();
+    }
+    let t_max = times.iter().fold(0.0f64, |max, t| max.max(t.abs()));
+    let steps = ((t_max * delta.abs() / 0.005).ceil() as usize).clamp(100, 5000);
+    let h = t_max / steps as f64;
+
+    // Weights of the start and the end of an interval [s, s + h] relative to exp(-nu s)
+    let z = hopping * h;
+    let end_weight = if z > 1e-8 {
+        (1.0 - (-z).exp() * (1.0 + z)) / (hopping * z)
+    } else {
+        h / 2.0
+    };
+    let start_weight = if z > 1e-8 {
+        (1.0 - (-z).exp()) / hopping - end_weight
+    } else {
+        h / 2.0
+    };
+    let decay: Vec<f64> = (0..=steps)
+        .map(|j| (-hopping * j as f64 * h).exp())
+        .collect();
+    let g_static: Vec<f64> = (0..=steps)
+        .map(|j| static_gauss_kt(delta, j as f64 * h))
+        .collect();
+    // Weight of node j when it is inside of the integration range
+    let inner: Vec<f64> = (0..=steps)
+        .map(|j| match j {
+            0 => start_weight,
+            _ => (decay[j] * start_weight + decay[j - 1] * end_weight) * g_static[j],
+        })
+        .collect();
+
+    let mut g = vec![1.0; steps + 1];
+    for n in 1..=steps {
+        let convolution: f64 = (1..n).map(|j| inner[j] * g[n - j]).sum::<f64>()
+            + decay[n - 1] * end_weight * g_static[n];
+        g[n] = (g_static[n] * decay[n] + hopping * convolution) / (1.0 - hopping * start_weight);
+    }
+
+    times
+        .iter()
+        .map(|t| {
+            if h == 0.0 {
+                return 1.0;
+            }
+            let x = t.abs() / h;
+            let i = (x.floor() as usize).min(steps.saturating_sub(1));
+            let fraction = x - i as f64;
+            g[i] + (g[(i + 1).min(steps)] - g[i]) * fraction
+        })
+        .collect()
+}
+
+// Bessel function of the first kind of order 0 (rational approximation of Numerical Recipes, accurate to
+// about 1e-8)
+fn bessel_j0(x: f64) -> f64 {
+    let ax = x.abs();
+    if ax < 8.0 {
+        let y = x * x;
+        let numerator = 57568490574.0
+            + y * (-13362590354.0
+                + y * (651619640.7 + y * (-11214424.18 + y * (77392.33017 + y * (-184.9052456)))));
+        let denominator = 57568490411.0
+            + y * (1029532985.0 + y * (9494680.718 + y * (59272.64853 + y * (267.8532712 + y))));
+        numerator / denominator
+    } else {
+        let z = 8.0 / ax;
+        let y = z * z;
+        let xx = ax - 0.785398164;
+        let p = 1.0
+            + y * (-0.1098628627e-2
+                + y * (0.2734510407e-4 + y * (-0.2073370639e-5 + y * 0.2093887211e-6)));
+        let q = -0.1562499995e-1
+            + y * (0.1430488765e-3
+                + y * (-0.6911147651e-5 + y * (0.7621095161e-6 - y * 0.934935152e-7)));
+        (FRAC_2_PI / ax).sqrt() * (xx.cos() * p - z * xx.sin() * q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions() {
+        let times = [0.0, 1.0, 2.0];
+        let parameters = [0.25, 0.5, 2.0, 90.0, 1.0];
+        let evaluate = |function: TheoryFunction| function.evaluate(&times, &parameters);
+
+        assert_eq!(evaluate(TheoryFunction::Asymmetry(0)), [0.25; 3]);
+        assert_eq!(evaluate(TheoryFunction::SimpleExp(1))[2], (-1.0f64).exp());
+        assert_eq!(evaluate(TheoryFunction::SimpleGauss(1))[2], (-0.5f64).exp());
+        // beta = 1 is an exponential
+        let stretched = evaluate(TheoryFunction::StretchedExp(1, 4));
+        assert!((stretched[2] - (-1.0f64).exp()).abs() < 1e-15);
+        let stretched = evaluate(TheoryFunction::StretchedExp(1, 2));
+        assert!((stretched[2] - (-1.0f64).exp()).abs() < 1e-15);
+        // 90° phase, 0.25 MHz: cos(pi t / 2 + pi / 2)
+        let cos = evaluate(TheoryFunction::TfCos(3, 0));
+        assert!(cos[0].abs() < 1e-15 && (cos[1] + 1.0).abs() < 1e-15);
+
+        let kt = evaluate(TheoryFunction::StaticGaussKt(4));
+        assert_eq!(kt[0], 1.0);
+        // Minimum at Delta t = sqrt(3)
+        assert!(static_gauss_kt(1.0, 3f64.sqrt()) < static_gauss_kt(1.0, 1.6));
+        assert!((static_gauss_kt(1.0, 100.0) - 1.0 / 3.0).abs() < 1e-12);
+
+        assert!((bessel_j0(0.0) - 1.0).abs() < 1e-8);
+        assert!(bessel_j0(2.404825557695773).abs() < 1e-7);
+        assert!((bessel_j0(10.0) + 0.2459357644513483).abs() < 1e-7);
+        assert_eq!(
+            evaluate(TheoryFunction::Bessel(3, 0))[0],
+            bessel_j0(PI / 2.0)
+        );
+    }
+
+    #[test]
+    fn dynamic_kubo_toyabe() {
+        let times: Vec<f64> = (0..=50).map(|i| i as f64 * 0.1).collect();
+        // No hopping is the static function
+        let g = dynamic_gauss_kt(1.0, 0.0, &times);
+        assert_eq!(g[20], static_gauss_kt(1.0, 2.0));
+        // Slow hopping stays close to the static function at early times
+        let g = dynamic_gauss_kt(1.0, 0.01, &times);
+        assert!((g[10] - static_gauss_kt(1.0, 1.0)).abs() < 1e-2);
+        // Fast hopping: motional narrowing as described by the Abragam function
+        let (delta, hopping) = (0.5, 20.0);
+        let g = dynamic_gauss_kt(delta, hopping, &times);
+        for (t, g) in times.iter().zip(g) {
+            let x = (-hopping * t).exp() - 1.0 + hopping * t;
+            let abragam = (-2.0 * delta * delta / (hopping * hopping) * x).exp();
+            assert!(
+                (g - abragam).abs() < 5e-4,
+                "t = {}: {} != {}",
+                t,
+                g,
+                abragam
+            );
+        }
+    }
+
+    #[test]
+    fn sum_of_products() {
+        let theory = Theory::new(vec![
+            vec![TheoryFunction::Asymmetry(0), TheoryFunction::SimpleExp(1)],
+            vec![TheoryFunction::Asymmetry(2)],
+        ]);
+        assert_eq!(theory.parameters(), [0, 1, 2]);
+        let values = theory.evaluate(&[0.0, 1.0], &[0.2, 1.0, 0.05]);
+        assert_eq!(values[0], 0.25);
+        assert_eq!(values[1], 0.2 * (-1.0f64).exp() + 0.05);
+    }
+}
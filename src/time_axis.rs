@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+use crate::error::AnalysisError;
+use crate::models::{Detector, HDecay};
+
+// Bin numbers of the run header refer to the bin contents starting at 0, i.e. bin i of a decay histogram
+// covers [i - 0.5, i + 0.5) on the x axis (xmin is -0.5). Times are measured from t0, which may lie
+// between two bins (fractional Time Zero Bin, e.g. at the high-field spectrometer).
+const NS_PER_US: f64 = 1000.0;
+
+impl Detector {
+    // Time Zero Bin, an error if the run header does not define it
+    pub fn t0_bin(&self) -> Result<f64, AnalysisError> {
+        self.time_zero_bin
+            .ok_or(AnalysisError::MissingTimeZeroBin(self.histo_number))
+    }
+
+    // Time of the center of `bin` relative to t0 in µs; `bin_width` in ns
+    pub fn time(&self, bin: usize, bin_width: f64) -> Result<f64, AnalysisError> {
+        Ok((bin as f64 - self.t0_bin()?) * bin_width / NS_PER_US)
+    }
+
+    // First Good Bin ..= Last Good Bin as a range, clamped to a histogram of `len` bins
+    pub fn good_bins(&self, len: usize) -> Range<usize> {
+        let clamp = |bin: i64| (bin.max(0) as usize).min(len);
+        let start = clamp(self.first_good_bin);
+        let end = clamp(self.last_good_bin.saturating_add(1));
+        start..end.max(start)
+    }
+}
+
+impl HDecay {
+    // Time axis in µs relative to t0 for all bins of the histogram
+    pub fn time_axis(&self, detector: &Detector) -> Result<Vec<f64>, AnalysisError> {
+        (0..self.contents.len())
+            .map(|bin| detector.time(bin, self.bin_width))
+            .collect()
+    }
+
+    // Counts within the good-bin window of the detector
+    pub fn good_counts(&self, detector: &Detector) -> &[f64] {
+        &self.contents[detector.good_bins(self.contents.len())]
+    }
+
+    // Time axis in µs relative to t0 within the good-bin window, matching `good_counts`
+    pub fn good_times(&self, detector: &Detector) -> Result<Vec<f64>, AnalysisError> {
+        detector
+            .good_bins(self.contents.len())
+            .map(|bin| detector.time(bin, self.bin_width))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::background::Background;
+    use crate::error::AnalysisError;
+    use crate::grouping::DetectorGroup;
+    use crate::max_ent::{MaxEnt, MaxEntDetector};
+    use crate::models::test_fixtures::{detector, h_decay};
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    #[test]
+    fn times_relative_to_t0() {
+        let h_decay = h_decay((0..10).map(f64::from).collect());
+        let times = h_decay.time_axis(&detector(2.0, 2, 9)).unwrap();
+        assert_eq!(times.len(), 10);
+        assert_eq!(times[2], 0.0);
+        assert_eq!(times[6], 4.0 * 0.1953125e-3);
+        assert_eq!(times[0], -2.0 * 0.1953125e-3);
+
+        // Fractional t0 as at the high-field spectrometer
+        let times = h_decay.time_axis(&detector(2.25, 3, 9)).unwrap();
+        assert!((times[3] - 0.75 * 0.1953125e-3).abs() < 1e-15);
+    }
+
+    #[test]
+    fn good_bin_window() {
+        let h_decay = h_decay((0..10).map(f64::from).collect());
+        let window = detector(2.0, 3, 6);
+        assert_eq!(h_decay.good_counts(&window), [3.0, 4.0, 5.0, 6.0]);
+        let times = h_decay.good_times(&window).unwrap();
+        assert_eq!(times.len(), 4);
+        assert_eq!(times[0], 0.1953125e-3);
+
+        // Windows reaching beyond the histogram are clamped
+        assert_eq!(detector(0.0, -5, 66600).good_bins(10), 0..10);
+        assert_eq!(detector(0.0, 8, 2).good_bins(10), 8..8);
+    }
+
+    #[tokio::test]
+    async fn missing_t0() {
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        let detector_info = &mut musr_root_file.run_header.detector_info;
+        detector_info.detectors[0].time_zero_bin = None;
+        let missing = Some(AnalysisError::MissingTimeZeroBin(1));
+
+        let h_decay = &musr_root_file.histos.decay_ana_module.h_decay[0];
+        let detector = h_decay.detector(detector_info).unwrap();
+        assert_eq!(h_decay.good_times(detector).err(), missing);
+        assert_eq!(
+            h_decay.background(detector, &Background::PreT0).err(),
+            missing
+        );
+        assert_eq!(
+            musr_root_file
+                .group(&DetectorGroup::new("forward", &[1, 2]))
+                .err(),
+            missing
+        );
+        let max_ent = MaxEnt {
+            detectors: vec![MaxEntDetector::new(1)],
+            field: (50.0, 150.0),
+            bins: 10,
+            range: (0.0, 4.0),
+            packing: 50,
+            background: Background::PreT0,
+            frames: 1e6,
+        };
+        assert_eq!(musr_root_file.max_ent(&max_ent).err(), missing);
+    }
+}
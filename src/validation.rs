@@ -0,0 +1,335 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::header_entry::HeaderEntry;
+use crate::models::MusrRootFile;
+
+// Rules of MusrRoot.xsd. The type tags are the ones of TMusrRunHeader:
+//  -@0 TString, -@1 Int_t, -@2 Double_t, -@3 TMusrRunPhysicalQuantity, -@4 TStringVector, -@5 TIntVector
+type Rule = (&'static str, u8, bool); // label, type tag, mandatory
+
+const RUN_INFO: [Rule; 25] = [
+    ("Version", 0, true),
+    ("Generic Validator URL", 0, true),
+    ("Specific Validator URL", 0, true),
+    ("Generator", 0, true),
+    ("Proposal Number", 1, false),
+    ("Main Proposer", 0, false),
+    ("File Name", 0, true),
+    ("Run Title", 0, true),
+    ("Run Number", 1, true),
+    ("Run Start Time", 0, true),
+    ("Run Stop Time", 0, true),
+    ("Run Duration", 3, true),
+    ("Laboratory", 0, true),
+    ("Instrument", 0, true),
+    ("Muon Beam Momentum", 3, true),
+    ("Muon Species", 0, true),
+    ("Muon Source", 0, true),
+    ("Setup", 0, true),
+    ("Comment", 0, true),
+    ("Sample Name", 0, true),
+    ("Sample Temperature", 3, true),
+    ("Sample Magnetic Field", 3, true),
+    ("No of Histos", 1, true),
+    ("Time Resolution", 3, true),
+    ("RedGreen Offsets", 5, true),
+];
+
+const DETECTOR: [Rule; 6] = [
+    ("Name", 0, true),
+    ("Histo Number", 1, true),
+    ("Histo Length", 1, true),
+    ("Time Zero Bin", 2, true),
+    ("First Good Bin", 1, true),
+    ("Last Good Bin", 1, true),
+];
+
+const SAMPLE_ENVIRONMENT_INFO: [Rule; 1] = [("Cryo", 0, true)];
+const MAGNETIC_FIELD_ENVIRONMENT_INFO: [Rule; 1] = [("Magnet Name", 0, true)];
+const BEAMLINE_INFO: [Rule; 1] = [("Name", 0, true)];
+
+// Every red / green mode in use, see `DecayAnaModule::modes`, holds as many decay histograms as this
+// RunInfo entry gives
+const HISTOS_PER_MODE: &str = "No of Histos";
+
+const HISTOGRAM_CLASS: &str = "TH1F";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning, // the file can be used, but something looks inconsistent
+    Error,   // the file violates MusrRoot.xsd
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub path: String, // folder or entry the finding is about, e.g. RunHeader/RunInfo/Run Number
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.path, self.message)
+    }
+}
+
+impl MusrRootFile {
+    // Check the file against the rules of MusrRoot.xsd and for internal consistency
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        self.validate_histos(&mut findings);
+        self.validate_run_header(&mut findings);
+        self.validate_histo_count(&mut findings);
+        findings
+    }
+
+    fn validate_histos(&self, findings: &mut Vec<Finding>) {
+        for module in ["DecayAnaModule", "SCAnaModule"] {
+            let path = format!("histos/{}", module);
+            let histograms = match self.histos.folders.get(module) {
+                Some(histograms) => histograms,
+                None => {
+                    findings.push(error(&path, "Mandatory folder is missing"));
+                    continue;
+                }
+            };
+            if histograms.is_empty() {
+                findings.push(error(&path, "Folder contains no histograms"));
+            }
+            for histogram in histograms {
+                let path = format!("{}/{}", path, histogram.name);
+                if module == "DecayAnaModule" && !is_decay_histo_name(&histogram.name) {
+                    findings.push(error(&path, "Name does not match hDecayXXX"));
+                }
+                if histogram.class_name != HISTOGRAM_CLASS {
+                    let message = format!(
+                        "Histogram is of type {} instead of {}",
+                        histogram.class_name, HISTOGRAM_CLASS
+                    );
+                    findings.push(error(&path, &message));
+                }
+            }
+        }
+    }
+
+    fn validate_run_header(&self, findings: &mut Vec<Finding>) {
+        let entries = &self.run_header.entries;
+        let folders = &self.run_header.folders;
+        let sections: [(&str, &[Rule]); 4] = [
+            ("RunInfo", &RUN_INFO),
+            ("SampleEnvironmentInfo", &SAMPLE_ENVIRONMENT_INFO),
+            (
+                "MagneticFieldEnvironmentInfo",
+                &MAGNETIC_FIELD_ENVIRONMENT_INFO,
+            ),
+            ("BeamlineInfo", &BEAMLINE_INFO),
+        ];
+        for (section, rules) in sections {
+            if !folders.iter().any(|folder| folder == section) {
+                findings.push(error(
+                    &format!("RunHeader/{}", section),
+                    "Mandatory folder is missing",
+                ));
+                continue;
+            }
+            let section_entries = entries.get(section).map(Vec::as_slice).unwrap_or(&[]);
+            validate_entries(
+                &format!("RunHeader/{}", section),
+                section_entries,
+                rules,
+                findings,
+            );
+        }
+
+        let detectors: Vec<_> = entries
+            .iter()
+            .filter(|(path, _)| path.starts_with("DetectorInfo/"))
+            .collect();
+        if !folders.iter().any(|folder| folder == "DetectorInfo") {
+            findings.push(error(
+                "RunHeader/DetectorInfo",
+                "Mandatory folder is missing",
+            ));
+        } else if detectors.is_empty() {
+            findings.push(error("RunHeader/DetectorInfo", "No detector defined"));
+        }
+        for (path, detector_entries) in detectors {
+            validate_entries(
+                &format!("RunHeader/{}", path),
+                detector_entries,
+                &DETECTOR,
+                findings,
+            );
+        }
+    }
+
+    fn validate_histo_count(&self, findings: &mut Vec<Finding>) {
+        let no_of_histos = self.run_header.run_info.no_of_histos;
+        for (offset, histograms) in self.histos.decay_ana_module.modes() {
+            if histograms.len() as i64 != no_of_histos {
+                let message = format!(
+                    "Red / green mode with offset {} holds {} decay histograms, but {} is {}",
+                    offset,
+                    histograms.len(),
+                    HISTOS_PER_MODE,
+                    no_of_histos
+                );
+                findings.push(warning("histos/DecayAnaModule", &message));
+            }
+        }
+    }
+}
+
+fn validate_entries(
+    path: &str,
+    entries: &[HeaderEntry],
+    rules: &[Rule],
+    findings: &mut Vec<Finding>,
+) {
+    for (label, type_tag, mandatory) in rules {
+        let path = format!("{}/{}", path, label);
+        match entries.iter().find(|entry| entry.label == *label) {
+            None if *mandatory => findings.push(error(&path, "Mandatory entry is missing")),
+            None => {}
+            Some(entry) if entry.type_tag != *type_tag => {
+                let message = format!(
+                    "Entry is of type -@{} instead of -@{}",
+                    entry.type_tag, type_tag
+                );
+                findings.push(error(&path, &message));
+            }
+            Some(entry) => {
+                if let Err(err) = entry.typed_value() {
+                    findings.push(error(&path, &err.to_string()));
+                }
+            }
+        }
+    }
+}
+
+// hDecayXXX, where XXX are at least 3 digits
+fn is_decay_histo_name(name: &str) -> bool {
+    match name.strip_prefix("hDecay") {
+        Some(number) => number.len() >= 3 && number.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+fn error(path: &str, message: &str) -> Finding {
+    Finding {
+        severity: Severity::Error,
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn warning(path: &str, message: &str) -> Finding {
+    Finding {
+        severity: Severity::Warning,
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use root_io::RootFile;
+
+    use super::*;
+    use crate::musr_root_file_parser::{parse_musr_root_file, read_folder};
+
+    #[tokio::test]
+    async fn validate_lem_run() {
+        let mut musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        // LEM fills 16 histograms into each of the red / green modes 0 and 40, No of Histos is 8
+        let findings = musr_root_file.validate();
+        assert_eq!(findings.len(), 2);
+        assert!(findings
+            .iter()
+            .all(|finding| finding.severity == Severity::Warning));
+        assert_eq!(
+            findings[1].message,
+            "Red / green mode with offset 40 holds 16 decay histograms, but No of Histos is 8"
+        );
+        musr_root_file.run_header.run_info.no_of_histos = 16;
+        assert_eq!(musr_root_file.validate(), vec![]);
+
+        musr_root_file.histos.folders.remove("SCAnaModule");
+        let run_info = musr_root_file
+            .run_header
+            .entries
+            .get_mut("RunInfo")
+            .unwrap();
+        run_info.retain(|entry| entry.label != "Run Number");
+        let no_of_histos = run_info
+            .iter_mut()
+            .find(|entry| entry.label == "No of Histos")
+            .unwrap();
+        no_of_histos.type_tag = 0;
+        musr_root_file.run_header.run_info.no_of_histos = 4;
+
+        let findings = musr_root_file.validate();
+        let paths: Vec<_> = findings.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "histos/SCAnaModule",
+                "RunHeader/RunInfo/Run Number",
+                "RunHeader/RunInfo/No of Histos",
+                "histos/DecayAnaModule",
+                "histos/DecayAnaModule",
+            ]
+        );
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[3].severity, Severity::Warning);
+        assert_eq!(
+            findings[2].to_string(),
+            "error: RunHeader/RunInfo/No of Histos: Entry is of type -@0 instead of -@1"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_run_header_folder() {
+        let file = RootFile::new(Path::new("./src/lem24_his_2000.root"))
+            .await
+            .expect("Failed to open file");
+        let histos = read_folder(file.items(), "histos").await.unwrap();
+        let mut run_header = read_folder(file.items(), "RunHeader").await.unwrap();
+        run_header
+            .items
+            .retain(|item| item.name() != "BeamlineInfo");
+
+        let musr_root_file = MusrRootFile::parse(&histos, &run_header).unwrap();
+        let errors: Vec<_> = musr_root_file
+            .validate()
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .collect();
+        assert_eq!(
+            errors,
+            [error(
+                "RunHeader/BeamlineInfo",
+                "Mandatory folder is missing"
+            )]
+        );
+    }
+
+    #[test]
+    fn decay_histo_names() {
+        assert!(is_decay_histo_name("hDecay001"));
+        assert!(is_decay_histo_name("hDecay1021"));
+        assert!(!is_decay_histo_name("hDecay01"));
+        assert!(!is_decay_histo_name("hSampleTemperature"));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::Write;
+
+use crate::header_entry::HeaderEntry;
+use crate::models::{HistoEntry, MusrRootFile};
+
+// The XML form of a MusrRoot file as checked by MusrRoot.xsd: the folder structure of the file where
+// histograms are listed with their name and ROOT class and every RunHeader entry becomes an element
+// named after its label (spaces replaced by `_`) containing the name of its type, e.g.
+//  <MusrRoot>
+//    <histos>
+//      <DecayAnaModule>
+//        <DecayHistoEntry>
+//          <HistoName>hDecay001</HistoName>
+//          <HistoType>TH1F</HistoType>
+//        </DecayHistoEntry>
+//        ...
+//    <RunHeader>
+//      <RunInfo>
+//        <Run_Number>Int_t</Run_Number>
+//        ...
+const RUN_HEADER_SECTIONS: [&str; 5] = [
+    "RunInfo",
+    "DetectorInfo",
+    "SampleEnvironmentInfo",
+    "MagneticFieldEnvironmentInfo",
+    "BeamlineInfo",
+];
+
+impl MusrRootFile {
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+        self.write_xml(&mut xml)
+            .expect("Writing to a String cannot fail");
+        xml
+    }
+
+    fn write_xml(&self, xml: &mut String) -> std::fmt::Result {
+        writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        let schema = &self.run_header.run_info.generic_validator_url;
+        if schema.is_empty() {
+            writeln!(xml, "<MusrRoot>")?;
+        } else {
+            writeln!(
+                xml,
+                r#"<MusrRoot xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="{}">"#,
+                escape(schema)
+            )?;
+        }
+        self.write_histos(xml)?;
+        self.write_run_header(xml)?;
+        writeln!(xml, "</MusrRoot>")
+    }
+
+    fn write_histos(&self, xml: &mut String) -> std::fmt::Result {
+        // DecayAnaModule and SCAnaModule have to come first
+        let folders = &self.histos.folders;
+        let mandatory = ["DecayAnaModule", "SCAnaModule"];
+        let names = mandatory
+            .into_iter()
+            .filter(|name| folders.contains_key(*name))
+            .chain(
+                folders
+                    .keys()
+                    .map(String::as_str)
+                    .filter(|name| !mandatory.contains(name)),
+            );
+
+        writeln!(xml, "  <histos>")?;
+        for folder in names {
+            let histograms = &folders[folder];
+            let (entry, name, class_name) = match folder {
+                "DecayAnaModule" => ("DecayHistoEntry", "HistoName", "HistoType"),
+                "SCAnaModule" => (
+                    "SlowControlHistoEntry",
+                    "SlowControlName",
+                    "SlowControlType",
+                ),
+                _ => ("HistoEntry", "HistoName", "HistoType"),
+            };
+            let folder = element_name(folder);
+            writeln!(xml, "    <{}>", folder)?;
+            for HistoEntry {
+                name: histo_name,
+                class_name: histo_class_name,
+            } in histograms
+            {
+                writeln!(xml, "      <{}>", entry)?;
+                writeln!(xml, "        <{0}>{1}</{0}>", name, escape(histo_name))?;
+                writeln!(
+                    xml,
+                    "        <{0}>{1}</{0}>",
+                    class_name,
+                    escape(histo_class_name)
+                )?;
+                writeln!(xml, "      </{}>", entry)?;
+            }
+            writeln!(xml, "    </{}>", folder)?;
+        }
+        writeln!(xml, "  </histos>")
+    }
+
+    fn write_run_header(&self, xml: &mut String) -> std::fmt::Result {
+        let entries = &self.run_header.entries;
+        let section_of = |path: &str| path.split('/').next().unwrap_or_default().to_string();
+        let mut sections: Vec<String> = RUN_HEADER_SECTIONS.iter().map(|s| s.to_string()).collect();
+        for path in entries.keys() {
+            let section = section_of(path);
+            if !sections.contains(&section) {
+                sections.push(section);
+            }
+        }
+
+        writeln!(xml, "  <RunHeader>")?;
+        for section in &sections {
+            writeln!(xml, "    <{}>", element_name(section))?;
+            for (path, section_entries) in entries.iter().filter(|(p, _)| section_of(p) == *section)
+            {
+                match path.split_once('/') {
+                    // Sub-folders, i.e. the detectors
+                    Some(_) => {
+                        let name = if section == "DetectorInfo" {
+                            "Detector".to_string()
+                        } else {
+                            element_name(path.rsplit('/').next().unwrap_or_default())
+                        };
+                        writeln!(xml, "      <{}>", name)?;
+                        write_entries(xml, section_entries, "        ")?;
+                        writeln!(xml, "      </{}>", name)?;
+                    }
+                    None => write_entries(xml, section_entries, "      ")?,
+                }
+            }
+            writeln!(xml, "    </{}>", element_name(section))?;
+        }
+        writeln!(xml, "  </RunHeader>")
+    }
+}
+
+fn write_entries(xml: &mut String, entries: &[HeaderEntry], indent: &str) -> std::fmt::Result {
+    for entry in entries {
+        let name = element_name(&entry.label);
+        writeln!(
+            xml,
+            "{}<{1}>{2}</{1}>",
+            indent,
+            name,
+            type_name(entry.type_tag)
+        )?;
+    }
+    Ok(())
+}
+
+fn type_name(type_tag: u8) -> &'static str {
+    match type_tag {
+        0 => "TString",
+        1 => "Int_t",
+        2 => "Double_t",
+        3 => "TMusrRunPhysicalQuantity",
+        4 => "TStringVector",
+        5 => "TIntVector",
+        6 => "TDoubleVector",
+        _ => "unknown",
+    }
+}
+
+// Turn a label like `No of Histos` into a valid element name like `No_of_Histos`
+fn element_name(label: &str) -> String {
+    let name: String = label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => name,
+        _ => format!("_{}", name),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::musr_root_file_parser::parse_musr_root_file;
+
+    #[tokio::test]
+    async fn export_lem_run() {
+        let musr_root_file = parse_musr_root_file("./src/lem24_his_2000.root")
+            .await
+            .expect("Failed to parse file");
+        let xml = musr_root_file.to_xml();
+
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(xml.contains(r#"xsi:noNamespaceSchemaLocation="http://lmu.web.psi.ch/facilities/software/MusrRoot/validation/MusrRoot.xsd""#));
+        assert!(xml.contains(
+            "      <DecayHistoEntry>\n        <HistoName>hDecay001</HistoName>\n        <HistoType>TH1F</HistoType>\n      </DecayHistoEntry>"
+        ));
+        assert!(xml.contains("<SlowControlName>Sample Temperature</SlowControlName>"));
+        assert!(xml.contains("      <Run_Number>Int_t</Run_Number>\n"));
+        assert!(xml.contains("<Time_Resolution>TMusrRunPhysicalQuantity</Time_Resolution>"));
+        assert!(xml.contains("<RedGreen_Offsets>TIntVector</RedGreen_Offsets>"));
+        assert!(xml.contains("<Moderator_HV>TMusrRunPhysicalQuantity</Moderator_HV>"));
+        assert_eq!(xml.matches("<Detector>").count(), 32);
+        assert!(xml.contains("        <Time_Zero_Bin>Double_t</Time_Zero_Bin>\n"));
+        assert!(xml.contains("    <ScalerInfo>"));
+        assert!(xml.find("<RunInfo>").unwrap() < xml.find("<DetectorInfo>").unwrap());
+        assert!(xml.ends_with("</MusrRoot>\n"));
+    }
+
+    #[test]
+    fn element_names() {
+        assert_eq!(element_name("No of Histos"), "No_of_Histos");
+        assert_eq!(element_name("P-Group"), "P-Group");
+        assert_eq!(element_name("Sample B (ZF)"), "Sample_B__ZF_");
+        assert_eq!(element_name("2nd Counter"), "_2nd_Counter");
+        assert_eq!(escape("a < b & c"), "a &lt; b &amp; c");
+    }
+}
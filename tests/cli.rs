@@ -0,0 +1,84 @@
+use std::env;
+use std::fs;
+use std::process::{Command, Output, Stdio};
+
+const FILE: &str = "./src/lem24_his_2000.root";
+
+fn plotting_data(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_plotting_data"))
+        .args(args)
+        .output()
+        .expect("Failed to run plotting_data")
+}
+
+#[test]
+fn success() {
+    let output = plotting_data(&["validate", FILE]);
+    assert_eq!(output.status.code(), Some(0));
+    // Only warnings, see the validation tests
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("warning: "));
+
+    let output = plotting_data(&["detectors", "--json", FILE]);
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["detectors"].as_array().unwrap().len(), 32);
+}
+
+#[test]
+fn detector_table() {
+    let output = plotting_data(&["detectors", FILE]);
+    assert_eq!(output.status.code(), Some(0));
+    let table = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 33);
+    // The longest name "e+ Bottom U(B) PostPileUp rej., EXT. OFF" has 40 characters
+    assert!(lines[0].starts_with(&format!("{:<40} Histo", "Name")));
+    assert!(lines
+        .iter()
+        .all(|line| line.chars().count() == lines[0].chars().count()));
+}
+
+#[test]
+fn invalid_arguments() {
+    // Two XML documents on stdout would not be a valid document
+    let output = plotting_data(&["export", FILE, FILE]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn unreadable_file() {
+    let output = plotting_data(&["header", "./src/missing.root", FILE]);
+    assert_eq!(output.status.code(), Some(2));
+    // The other files are still processed
+    assert!(String::from_utf8_lossy(&output.stdout).contains("lem24_his_2000.root <=="));
+}
+
+#[test]
+fn unwritable_output() {
+    let dir = env::temp_dir().join("plotting_data_cli_missing");
+    let _ = fs::remove_dir_all(&dir);
+    let output = plotting_data(&["export", "-o", dir.to_str().unwrap(), FILE]);
+    assert_eq!(output.status.code(), Some(3));
+
+    fs::create_dir_all(&dir).unwrap();
+    let output = plotting_data(&["export", "-o", dir.to_str().unwrap(), FILE, FILE]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.join("lem24_his_2000.xml").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn closed_pipe() {
+    // More output than fits into the pipe, which is closed before it is read
+    let mut child = Command::new(env!("CARGO_BIN_EXE_plotting_data"))
+        .args(["header", FILE, FILE, FILE, FILE])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run plotting_data");
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}